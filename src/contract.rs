@@ -1,12 +1,47 @@
 use crate::error::ContractError;
+use crate::execute::against::{exec_buy_against, exec_sell_against};
+use crate::execute::batch::exec_batch;
 use crate::execute::buy::exec_buy;
+use crate::execute::buy_exact_out::exec_buy_exact_out;
 use crate::execute::claim::exec_claim;
+use crate::execute::complete_set::{exec_mint_set, exec_redeem_set};
+use crate::execute::deposit::exec_deposit;
+use crate::execute::grant::{exec_grant_trader, exec_revoke};
+use crate::execute::pause::exec_pause;
+use crate::execute::register_pubkey::exec_register_pubkey;
+use crate::execute::relay::exec_relay;
+use crate::execute::resolve::exec_resolve;
+use crate::execute::roles::{exec_grant_role, exec_revoke_role};
 use crate::execute::sell::exec_sell;
+use crate::execute::sell_all::exec_sell_all;
+use crate::execute::sell_exact_out::exec_sell_exact_out;
+use crate::execute::set_fees::exec_set_fees;
 use crate::execute::swap::exec_swap;
-use crate::execute::Context;
+use crate::execute::swap_multi::exec_swap_multi;
+use crate::execute::update_config::exec_update_config;
+use crate::execute::withdraw::exec_withdraw;
+use crate::execute::{last_trade_seq, trade_seq_attributes, Context};
 use crate::msg::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg};
+use crate::query::candles::query_candles;
 use crate::query::config::query_config;
+use crate::query::grants::query_grants;
+use crate::query::holders::query_holders;
+use crate::query::leaderboard::query_leaderboard;
+use crate::query::odds::query_odds;
+use crate::query::pause_status::query_pause_status;
+use crate::query::payouts::query_payouts;
+use crate::query::pool_stats::query_pool_stats;
 use crate::query::pools::query_pools;
+use crate::query::positions::query_positions;
+use crate::query::probability_history::query_probability_history;
+use crate::query::quote_balance::query_quote_balance;
+use crate::query::recent_trades::query_recent_trades;
+use crate::query::roles::query_role_members;
+use crate::query::signer::query_signer;
+use crate::query::simulate_against::query_simulate_buy_against;
+use crate::query::solvency::query_solvency;
+use crate::query::swap_flows::query_swap_flows;
+use crate::query::trade_history::query_trade_history;
 use crate::query::trader::query_trader;
 use crate::query::ReadonlyContext;
 use crate::state;
@@ -25,23 +60,57 @@ pub fn instantiate(
     msg: InstantiateMsg,
 ) -> Result<Response, ContractError> {
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
-    Ok(state::init(Context { deps, env, info }, &msg)?)
+    state::init(Context { deps, env, info }, &msg)
 }
 
 #[entry_point]
 pub fn execute(
-    deps: DepsMut,
+    mut deps: DepsMut,
     env: Env,
     info: MessageInfo,
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
-    let ctx = Context { deps, env, info };
-    match msg {
+    let prev_seq = last_trade_seq(deps.storage)?;
+    let ctx = Context {
+        deps: deps.branch(),
+        env,
+        info,
+    };
+    let resp = match msg {
         ExecuteMsg::Buy(params) => exec_buy(ctx, params),
         ExecuteMsg::Sell(params) => exec_sell(ctx, params),
         ExecuteMsg::Swap(params) => exec_swap(ctx, params),
+        ExecuteMsg::BuyExactOut(params) => exec_buy_exact_out(ctx, params),
+        ExecuteMsg::SellExactOut(params) => exec_sell_exact_out(ctx, params),
+        ExecuteMsg::Batch(params) => exec_batch(ctx, params),
+        ExecuteMsg::SwapMulti(params) => exec_swap_multi(ctx, params),
+        ExecuteMsg::MintSet(params) => exec_mint_set(ctx, params),
+        ExecuteMsg::RedeemSet(params) => exec_redeem_set(ctx, params),
+        ExecuteMsg::BuyAgainst(params) => exec_buy_against(ctx, params),
+        ExecuteMsg::SellAgainst(params) => exec_sell_against(ctx, params),
+        ExecuteMsg::SellAll(params) => exec_sell_all(ctx, params),
+        ExecuteMsg::Deposit {} => exec_deposit(ctx),
+        ExecuteMsg::Withdraw { amount } => exec_withdraw(ctx, amount),
         ExecuteMsg::Claim {} => exec_claim(ctx),
-    }
+        ExecuteMsg::RegisterPubkey { pubkey } => exec_register_pubkey(ctx, pubkey),
+        ExecuteMsg::Relay(params) => exec_relay(ctx, params),
+        ExecuteMsg::GrantTrader {
+            grantee,
+            expires,
+            max_quote_spend,
+            allowed_actions,
+        } => exec_grant_trader(ctx, grantee, expires, max_quote_spend, allowed_actions),
+        ExecuteMsg::Revoke { grantee } => exec_revoke(ctx, grantee),
+        ExecuteMsg::GrantRole { role, address } => exec_grant_role(ctx, role, address),
+        ExecuteMsg::RevokeRole { role, address } => exec_revoke_role(ctx, role, address),
+        ExecuteMsg::SetFees(fees) => exec_set_fees(ctx, fees),
+        ExecuteMsg::UpdateConfig(config) => exec_update_config(ctx, config),
+        ExecuteMsg::Resolve { pool_id } => exec_resolve(ctx, pool_id),
+        ExecuteMsg::Pause { target } => exec_pause(ctx, target, true),
+        ExecuteMsg::Unpause { target } => exec_pause(ctx, target, false),
+    }?;
+    // Tag the response with the sequence numbers of any trades it made
+    Ok(resp.add_attributes(trade_seq_attributes(deps.storage, prev_seq)?))
 }
 
 #[entry_point]
@@ -55,16 +124,967 @@ pub fn query(
         QueryMsg::Config {} => to_json_binary(&query_config(ctx)?),
         QueryMsg::Pools {} => to_json_binary(&query_pools(ctx)?),
         QueryMsg::Trader { address } => to_json_binary(&query_trader(ctx, address)?),
+        QueryMsg::Signer { address } => to_json_binary(&query_signer(ctx, address)?),
+        QueryMsg::Grants { granter } => to_json_binary(&query_grants(ctx, granter)?),
+        QueryMsg::RoleMembers { role } => to_json_binary(&query_role_members(ctx, role)?),
+        QueryMsg::PauseStatus {} => to_json_binary(&query_pause_status(ctx)?),
+        QueryMsg::SimulateBuyAgainst { pool_id, amount } => {
+            to_json_binary(&query_simulate_buy_against(ctx, pool_id, amount)?)
+        },
+        QueryMsg::QuoteBalance { address } => to_json_binary(&query_quote_balance(ctx, address)?),
+        QueryMsg::Solvency {} => to_json_binary(&query_solvency(ctx)?),
+        QueryMsg::Positions { address } => to_json_binary(&query_positions(ctx, address)?),
+        QueryMsg::Payouts { address } => to_json_binary(&query_payouts(ctx, address)?),
+        QueryMsg::Odds {} => to_json_binary(&query_odds(ctx)?),
+        QueryMsg::Candles {
+            pool_id,
+            resolution,
+            start,
+            end,
+            limit,
+        } => to_json_binary(&query_candles(ctx, pool_id, resolution, start, end, limit)?),
+        QueryMsg::ProbabilityHistory {
+            start,
+            end,
+            interval,
+            limit,
+        } => to_json_binary(&query_probability_history(
+            ctx, start, end, interval, limit,
+        )?),
+        QueryMsg::RecentTrades { since_seq, limit } => {
+            to_json_binary(&query_recent_trades(ctx, since_seq, limit)?)
+        },
+        QueryMsg::SwapFlows {} => to_json_binary(&query_swap_flows(ctx)?),
+        QueryMsg::Holders {
+            pool_id,
+            start_after,
+            limit,
+        } => to_json_binary(&query_holders(ctx, pool_id, start_after, limit)?),
+        QueryMsg::Leaderboard { by, limit } => to_json_binary(&query_leaderboard(ctx, by, limit)?),
+        QueryMsg::PoolStats { pool_id } => to_json_binary(&query_pool_stats(ctx, pool_id)?),
+        QueryMsg::TradeHistory {
+            address,
+            start_after,
+            limit,
+        } => to_json_binary(&query_trade_history(ctx, address, start_after, limit)?),
     }?;
     Ok(result)
 }
 
 #[entry_point]
 pub fn migrate(
-    deps: DepsMut,
+    mut deps: DepsMut,
     _env: Env,
-    _msg: MigrateMsg,
+    msg: MigrateMsg,
 ) -> Result<Response, ContractError> {
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    state::migrate_roles(deps.branch(), msg.admin)?;
+    state::reindex(deps.storage)?;
     Ok(Response::default())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::msg::{
+        AgainstParams, BatchParams, BuyParams, CompleteSetParams, FeeInitArgs, GrantsResponse,
+        HoldersResponse, IntentAction, IntentSignDoc, LeaderboardMetric, LeaderboardResponse,
+        PoolAmount, PoolInitArgs, PoolsResponse, RecentTradesResponse, RelayParams, Role,
+        SellParams, SolvencyResponse, SwapParams, TradeAction, TradeHistoryResponse, TradeIntent,
+        TradeKind, TradeOp, TraderResponse,
+    };
+    use crate::state::models::PoolReserves;
+    use crate::token::Token;
+    use cosmwasm_std::testing::{
+        mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MockStorage,
+        MOCK_CONTRACT_ADDR,
+    };
+    use cosmwasm_std::{
+        coins, from_json, to_json_vec, Addr, BankMsg, Binary, CosmosMsg, Order, OwnedDeps, Storage,
+        SubMsg, Timestamp, Uint128,
+    };
+
+    type TestDeps = OwnedDeps<MockStorage, MockApi, MockQuerier>;
+
+    const DENOM: &str = "uusd";
+    const TRADER: &str = "trader";
+    /// Fee of 1%, where 1,000,000 is 100%.
+    const FEE_PCT: u128 = 10_000;
+
+    fn setup() -> TestDeps {
+        setup_with_fee(FEE_PCT)
+    }
+
+    fn setup_with_fee(fee_pct: u128) -> TestDeps {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let pool = |symbol: &str| PoolInitArgs {
+            symbol: symbol.to_owned(),
+            name: symbol.to_owned(),
+            description: None,
+            image: None,
+            reserves: PoolReserves {
+                base: Uint128::new(1_000_000_000),
+                quote: Uint128::new(1_000_000_000),
+            },
+        };
+        let msg = InstantiateMsg {
+            operator: None,
+            start: env.block.time,
+            stop: env.block.time.plus_days(1),
+            quote_token: Token::Denom(DENOM.to_owned()),
+            quote_decimals: 6,
+            quote_symbol: "USD".to_owned(),
+            pools: vec![pool("YES"), pool("NO")],
+            fees: FeeInitArgs {
+                manager: None,
+                pct_swap: Uint128::new(fee_pct),
+                pct_buy: Uint128::new(fee_pct),
+                pct_sell: Uint128::new(fee_pct),
+                pct_claim: None,
+            },
+            config: None,
+        };
+        instantiate(deps.as_mut(), env, mock_info("admin", &[]), msg).unwrap();
+        deps
+    }
+
+    fn exec(
+        deps: &mut TestDeps,
+        sender: &str,
+        funds: u128,
+        msg: ExecuteMsg,
+    ) -> Result<Response, ContractError> {
+        exec_at(deps, mock_env(), sender, funds, msg)
+    }
+
+    fn exec_at(
+        deps: &mut TestDeps,
+        env: Env,
+        sender: &str,
+        funds: u128,
+        msg: ExecuteMsg,
+    ) -> Result<Response, ContractError> {
+        let funds = if funds == 0 {
+            vec![]
+        } else {
+            coins(funds, DENOM)
+        };
+        // Roll back storage on failure, as the chain would
+        let snapshot: Vec<_> = deps.storage.range(None, None, Order::Ascending).collect();
+        let result = execute(deps.as_mut(), env, mock_info(sender, &funds), msg);
+        if result.is_err() {
+            let keys: Vec<_> = deps
+                .storage
+                .range(None, None, Order::Ascending)
+                .map(|(k, _)| k)
+                .collect();
+            for key in keys {
+                deps.storage.remove(&key);
+            }
+            for (key, value) in snapshot {
+                deps.storage.set(&key, &value);
+            }
+        }
+        result
+    }
+
+    fn buy_msg(
+        pool_id: u8,
+        amount: u128,
+    ) -> ExecuteMsg {
+        ExecuteMsg::Buy(BuyParams {
+            initiator: None,
+            amounts: vec![PoolAmount {
+                pool_id,
+                amount: Uint128::new(amount),
+            }],
+            deadline: None,
+            valid_until_height: None,
+            use_internal_balance: None,
+        })
+    }
+
+    fn sell_msg(
+        pool_id: u8,
+        amount: Uint128,
+    ) -> ExecuteMsg {
+        ExecuteMsg::Sell(SellParams {
+            initiator: None,
+            amounts: vec![PoolAmount { pool_id, amount }],
+            deadline: None,
+            valid_until_height: None,
+            use_internal_balance: None,
+        })
+    }
+
+    fn set_msg(amount: u128) -> CompleteSetParams {
+        CompleteSetParams {
+            initiator: None,
+            amount: Uint128::new(amount),
+            deadline: None,
+            valid_until_height: None,
+            use_internal_balance: None,
+        }
+    }
+
+    fn buy(
+        deps: &mut TestDeps,
+        pool_id: u8,
+        amount: u128,
+    ) {
+        exec(deps, TRADER, amount, buy_msg(pool_id, amount)).unwrap();
+    }
+
+    fn sell(
+        deps: &mut TestDeps,
+        pool_id: u8,
+        amount: Uint128,
+    ) {
+        exec(deps, TRADER, 0, sell_msg(pool_id, amount)).unwrap();
+    }
+
+    fn query_pools(deps: &TestDeps) -> PoolsResponse {
+        from_json(query(deps.as_ref(), mock_env(), QueryMsg::Pools {}).unwrap()).unwrap()
+    }
+
+    fn query_trader_of(
+        deps: &TestDeps,
+        address: &str,
+    ) -> TraderResponse {
+        let msg = QueryMsg::Trader {
+            address: Addr::unchecked(address),
+        };
+        from_json(query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap()
+    }
+
+    fn query_trader(deps: &TestDeps) -> TraderResponse {
+        query_trader_of(deps, TRADER)
+    }
+
+    fn balance_of(
+        deps: &TestDeps,
+        address: &str,
+        pool_id: u8,
+    ) -> Uint128 {
+        query_trader_of(deps, address)
+            .balances
+            .iter()
+            .find(|b| b.pool == pool_id)
+            .map(|b| b.amount)
+            .unwrap_or_default()
+    }
+
+    fn balance(
+        deps: &TestDeps,
+        pool_id: u8,
+    ) -> Uint128 {
+        balance_of(deps, TRADER, pool_id)
+    }
+
+    #[test]
+    fn sells_are_counted_separately_from_buys() {
+        let mut deps = setup();
+        buy(&mut deps, 0, 1_000_000);
+        buy(&mut deps, 0, 1_000_000);
+        let amount = balance(&deps, 0).multiply_ratio(1u128, 2u128);
+        sell(&mut deps, 0, amount);
+
+        let pools = query_pools(&deps);
+        assert_eq!(pools.pools[0].stats.num_buys, 2);
+        assert_eq!(pools.pools[0].stats.num_sells, 1);
+        assert_eq!(pools.stats.num_buys, 2);
+        assert_eq!(pools.stats.num_sells, 1);
+
+        let trader = query_trader(&deps);
+        assert_eq!(trader.stats.num_buys, 2);
+        assert_eq!(trader.stats.num_sells, 1);
+    }
+
+    #[test]
+    fn reentering_traders_are_counted_once() {
+        let mut deps = setup();
+        buy(&mut deps, 0, 1_000_000);
+        let amount = balance(&deps, 0);
+        sell(&mut deps, 0, amount);
+        buy(&mut deps, 0, 1_000_000);
+
+        let pools = query_pools(&deps);
+        assert_eq!(pools.pools[0].stats.num_traders, 1);
+        assert_eq!(pools.stats.num_traders, 1);
+    }
+
+    #[test]
+    fn swaps_update_trader_stats() {
+        let mut deps = setup();
+        buy(&mut deps, 0, 1_000_000);
+        let msg = ExecuteMsg::Swap(SwapParams {
+            initiator: None,
+            from_pool: 0,
+            to_pool: 1,
+            from_amount: balance(&deps, 0),
+            deadline: None,
+            valid_until_height: None,
+        });
+        execute(deps.as_mut(), mock_env(), mock_info(TRADER, &[]), msg).unwrap();
+
+        let trader = query_trader(&deps);
+        assert_eq!(trader.stats.num_swaps, 1);
+        assert_eq!(trader.stats.num_buys, 1);
+        assert_eq!(trader.stats.num_sells, 0);
+
+        let pools = query_pools(&deps);
+        assert_eq!(pools.stats.num_swaps, 1);
+        assert_eq!(pools.pools[0].stats.num_swaps, 1);
+        assert_eq!(pools.pools[1].stats.num_swaps, 1);
+    }
+
+    #[test]
+    fn minted_sets_cannot_be_sold_out_of_pool_offsets() {
+        let mut deps = setup();
+        exec(
+            &mut deps,
+            TRADER,
+            1_000_000,
+            ExecuteMsg::MintSet(set_msg(1_000_000)),
+        )
+        .unwrap();
+
+        // Nobody has bought into either pool, so there is no quote to sell into
+        for pool_id in 0..2 {
+            let err = exec(
+                &mut deps,
+                TRADER,
+                0,
+                sell_msg(pool_id, Uint128::new(1_000_000)),
+            );
+            assert!(matches!(
+                err,
+                Err(ContractError::InsufficientBalance { .. })
+            ));
+        }
+
+        // Once another trader buys in, minted units can be sold up to the quote they added
+        exec(&mut deps, "buyer", 2_000_000, buy_msg(0, 2_000_000)).unwrap();
+        exec(&mut deps, TRADER, 0, sell_msg(0, Uint128::new(500_000))).unwrap();
+        for pool in query_pools(&deps).pools {
+            assert!(pool.reserves.quote >= pool.offset);
+        }
+
+        // Claims and solvency remain computable
+        let solvency: SolvencyResponse =
+            from_json(query(deps.as_ref(), mock_env(), QueryMsg::Solvency {}).unwrap()).unwrap();
+        assert!(!solvency.liabilities.is_zero());
+        exec(
+            &mut deps,
+            TRADER,
+            0,
+            ExecuteMsg::RedeemSet(set_msg(500_000)),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn buys_send_one_fee_transfer_and_none_when_fees_are_zero() {
+        let mut deps = setup_with_fee(0);
+        let resp = exec(&mut deps, TRADER, 1_000_000, buy_msg(0, 1_000_000)).unwrap();
+        assert!(resp.messages.is_empty());
+
+        let mut deps = setup();
+        let msg = ExecuteMsg::Buy(BuyParams {
+            initiator: None,
+            amounts: vec![
+                PoolAmount {
+                    pool_id: 0,
+                    amount: Uint128::new(1_000_000),
+                },
+                PoolAmount {
+                    pool_id: 1,
+                    amount: Uint128::new(1_000_000),
+                },
+            ],
+            deadline: None,
+            valid_until_height: None,
+            use_internal_balance: None,
+        });
+        let resp = exec(&mut deps, TRADER, 2_000_000, msg).unwrap();
+        assert_eq!(
+            resp.messages,
+            vec![SubMsg::new(BankMsg::Send {
+                to_address: "admin".to_owned(),
+                amount: coins(20_000, DENOM),
+            })]
+        );
+    }
+
+    #[test]
+    fn swaps_skip_the_fee_transfer_when_fees_are_zero() {
+        let mut deps = setup_with_fee(0);
+        buy(&mut deps, 0, 1_000_000);
+        let msg = ExecuteMsg::Swap(SwapParams {
+            initiator: None,
+            from_pool: 0,
+            to_pool: 1,
+            from_amount: balance(&deps, 0),
+            deadline: None,
+            valid_until_height: None,
+        });
+        let resp = exec(&mut deps, TRADER, 0, msg).unwrap();
+        assert!(resp.messages.is_empty());
+    }
+
+    fn against_msg(
+        pool_id: u8,
+        amount: u128,
+    ) -> AgainstParams {
+        AgainstParams {
+            initiator: None,
+            pool_id,
+            amount: Uint128::new(amount),
+            deadline: None,
+            valid_until_height: None,
+            use_internal_balance: None,
+        }
+    }
+
+    #[test]
+    fn plain_sells_shrink_legs_of_positions_against_other_pools() {
+        let mut deps = setup();
+        exec(
+            &mut deps,
+            TRADER,
+            1_000_000,
+            ExecuteMsg::BuyAgainst(against_msg(1, 1_000_000)),
+        )
+        .unwrap();
+        let leg_amount = query_trader(&deps).against[0].position.legs[0].amount;
+        assert_eq!(leg_amount, balance(&deps, 0));
+
+        // Units bought directly are sold first without touching the leg
+        buy(&mut deps, 0, 1_000_000);
+        let direct = balance(&deps, 0) - leg_amount;
+        sell(&mut deps, 0, direct);
+        assert_eq!(
+            query_trader(&deps).against[0].position.legs[0].amount,
+            leg_amount
+        );
+
+        // Selling into the leg shrinks it to what remains
+        sell(&mut deps, 0, leg_amount.multiply_ratio(1u128, 2u128));
+        let trader = query_trader(&deps);
+        assert_eq!(trader.against[0].position.legs[0].amount, balance(&deps, 0));
+
+        // Selling the rest removes the position
+        let rest = balance(&deps, 0);
+        sell(&mut deps, 0, rest);
+        assert!(query_trader(&deps).against.is_empty());
+        let err = exec(
+            &mut deps,
+            TRADER,
+            0,
+            ExecuteMsg::SellAgainst(against_msg(1, 1)),
+        );
+        assert!(matches!(err, Err(ContractError::NotAuthorized { .. })));
+    }
+
+    #[test]
+    fn holders_and_position_leaderboard_rank_one_pool_by_balance() {
+        let mut deps = setup();
+        for (trader, pool_id, amount) in [
+            ("a", 0, 1_000_000),
+            ("b", 0, 3_000_000),
+            ("c", 0, 2_000_000),
+            ("d", 1, 9_000_000),
+        ] {
+            exec(&mut deps, trader, amount, buy_msg(pool_id, amount)).unwrap();
+        }
+        let c_balance = balance_of(&deps, "c", 0);
+        exec(&mut deps, "c", 0, sell_msg(0, c_balance)).unwrap();
+
+        let holders = |start_after: Option<&str>, limit: u32| -> Vec<String> {
+            let msg = QueryMsg::Holders {
+                pool_id: 0,
+                start_after: start_after.map(Addr::unchecked),
+                limit: Some(limit),
+            };
+            let resp: HoldersResponse =
+                from_json(query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+            resp.holders
+                .into_iter()
+                .map(|h| h.address.to_string())
+                .collect()
+        };
+        assert_eq!(holders(None, 10), vec!["b", "a"]);
+        assert_eq!(holders(None, 1), vec!["b"]);
+        assert_eq!(holders(Some("b"), 1), vec!["a"]);
+        assert!(holders(Some("a"), 1).is_empty());
+
+        let msg = QueryMsg::Leaderboard {
+            by: LeaderboardMetric::Position { pool_id: 0 },
+            limit: None,
+        };
+        let resp: LeaderboardResponse =
+            from_json(query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        let ranked: Vec<_> = resp
+            .entries
+            .iter()
+            .map(|e| (e.address.as_str(), e.pool_id))
+            .collect();
+        assert_eq!(ranked, vec![("b", Some(0)), ("a", Some(0))]);
+    }
+
+    #[test]
+    fn complete_sets_are_recorded_in_trade_histories() {
+        let mut deps = setup();
+        exec(
+            &mut deps,
+            TRADER,
+            1_000_000,
+            ExecuteMsg::MintSet(set_msg(1_000_000)),
+        )
+        .unwrap();
+        exec(
+            &mut deps,
+            TRADER,
+            0,
+            ExecuteMsg::RedeemSet(set_msg(400_000)),
+        )
+        .unwrap();
+
+        let msg = QueryMsg::TradeHistory {
+            address: Addr::unchecked(TRADER),
+            start_after: None,
+            limit: None,
+        };
+        let resp: TradeHistoryResponse =
+            from_json(query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        let trades: Vec<_> = resp
+            .trades
+            .iter()
+            .map(|entry| {
+                (
+                    entry.trade.action,
+                    entry.trade.pool_ids.clone(),
+                    entry.trade.in_amount,
+                )
+            })
+            .collect();
+        assert_eq!(
+            trades,
+            vec![
+                (TradeAction::RedeemSet, vec![0, 1], Uint128::new(400_000)),
+                (TradeAction::MintSet, vec![0, 1], Uint128::new(1_000_000)),
+            ]
+        );
+
+        let msg = QueryMsg::RecentTrades {
+            since_seq: None,
+            limit: None,
+        };
+        let resp: RecentTradesResponse =
+            from_json(query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        assert_eq!(resp.last_seq, 2);
+    }
+
+    const SIGNER: &str = "signer";
+    const RELAYER: &str = "relayer";
+
+    fn signing_key() -> k256::ecdsa::SigningKey {
+        k256::ecdsa::SigningKey::from_slice(&[7u8; 32]).unwrap()
+    }
+
+    /// Set up a relayer and a signer with a registered pubkey and an internal
+    /// quote balance to trade with.
+    fn setup_relay() -> TestDeps {
+        let mut deps = setup();
+        let msg = ExecuteMsg::GrantRole {
+            role: Role::Relayer,
+            address: Addr::unchecked(RELAYER),
+        };
+        exec(&mut deps, "admin", 0, msg).unwrap();
+        let pubkey = signing_key().verifying_key().to_encoded_point(true);
+        let msg = ExecuteMsg::RegisterPubkey {
+            pubkey: Binary::from(pubkey.as_bytes()),
+        };
+        exec(&mut deps, SIGNER, 0, msg).unwrap();
+        exec(&mut deps, SIGNER, 10_000_000, ExecuteMsg::Deposit {}).unwrap();
+        deps
+    }
+
+    /// Relay a buy of 1,000,000 quote from pool 0, signed by key.
+    fn relay_msg(
+        key: &k256::ecdsa::SigningKey,
+        nonce: u64,
+        expires: Timestamp,
+    ) -> ExecuteMsg {
+        use k256::ecdsa::{signature::hazmat::PrehashSigner, Signature};
+        use sha2::Digest;
+
+        let intent = TradeIntent {
+            signer: Addr::unchecked(SIGNER),
+            nonce,
+            expires,
+            action: IntentAction::Buy(BuyParams {
+                initiator: None,
+                amounts: vec![PoolAmount {
+                    pool_id: 0,
+                    amount: Uint128::new(1_000_000),
+                }],
+                deadline: None,
+                valid_until_height: None,
+                use_internal_balance: Some(true),
+            }),
+        };
+        let env = mock_env();
+        let sign_doc = IntentSignDoc {
+            chain_id: env.block.chain_id,
+            contract: env.contract.address,
+            intent: intent.clone(),
+        };
+        let hash = sha2::Sha256::digest(to_json_vec(&sign_doc).unwrap());
+        let signature: Signature = key.sign_prehash(&hash).unwrap();
+        ExecuteMsg::Relay(RelayParams {
+            intent,
+            signature: Binary::from(signature.to_bytes().as_slice()),
+        })
+    }
+
+    #[test]
+    fn relayed_intents_execute_once_in_nonce_order() {
+        let mut deps = setup_relay();
+        let expires = mock_env().block.time.plus_seconds(60);
+
+        // Nonces must be used in order
+        let err = exec(&mut deps, RELAYER, 0, relay_msg(&signing_key(), 1, expires));
+        assert!(matches!(err, Err(ContractError::ValidationError { .. })));
+
+        exec(&mut deps, RELAYER, 0, relay_msg(&signing_key(), 0, expires)).unwrap();
+        assert_eq!(query_trader_of(&deps, SIGNER).stats.num_buys, 1);
+
+        // A relayed intent cannot be replayed
+        let err = exec(&mut deps, RELAYER, 0, relay_msg(&signing_key(), 0, expires));
+        assert!(matches!(err, Err(ContractError::ValidationError { .. })));
+
+        exec(&mut deps, RELAYER, 0, relay_msg(&signing_key(), 1, expires)).unwrap();
+        assert_eq!(query_trader_of(&deps, SIGNER).stats.num_buys, 2);
+    }
+
+    #[test]
+    fn relayed_intents_reject_bad_signatures_expiry_and_unknown_signers() {
+        let mut deps = setup_relay();
+        let expires = mock_env().block.time.plus_seconds(60);
+
+        // Signed by a key other than the registered one
+        let other_key = k256::ecdsa::SigningKey::from_slice(&[9u8; 32]).unwrap();
+        let err = exec(&mut deps, RELAYER, 0, relay_msg(&other_key, 0, expires));
+        assert!(
+            matches!(err, Err(ContractError::NotAuthorized { msg }) if msg == "invalid intent signature")
+        );
+
+        let expired = mock_env().block.time.minus_seconds(1);
+        let err = exec(&mut deps, RELAYER, 0, relay_msg(&signing_key(), 0, expired));
+        assert!(matches!(err, Err(ContractError::Expired { .. })));
+
+        // Only relayers may relay
+        let err = exec(
+            &mut deps,
+            "stranger",
+            0,
+            relay_msg(&signing_key(), 0, expires),
+        );
+        assert!(matches!(err, Err(ContractError::NotAuthorized { .. })));
+
+        // Signers must have registered a pubkey
+        let mut deps = setup();
+        let msg = ExecuteMsg::GrantRole {
+            role: Role::Relayer,
+            address: Addr::unchecked(RELAYER),
+        };
+        exec(&mut deps, "admin", 0, msg).unwrap();
+        let err = exec(&mut deps, RELAYER, 0, relay_msg(&signing_key(), 0, expires));
+        assert!(
+            matches!(err, Err(ContractError::NotAuthorized { msg }) if msg.starts_with("no pubkey"))
+        );
+    }
+
+    const GRANTER: &str = "granter";
+    const GRANTEE: &str = "grantee";
+
+    fn grant(
+        deps: &mut TestDeps,
+        expires: Option<Timestamp>,
+        max_quote_spend: Option<u128>,
+    ) {
+        let msg = ExecuteMsg::GrantTrader {
+            grantee: Addr::unchecked(GRANTEE),
+            expires,
+            max_quote_spend: max_quote_spend.map(Uint128::new),
+            allowed_actions: vec![TradeKind::Buy],
+        };
+        exec(deps, GRANTER, 0, msg).unwrap();
+    }
+
+    /// Buy amount of quote from pool 0 on behalf of the granter.
+    fn granted_buy_msg(amount: u128) -> ExecuteMsg {
+        ExecuteMsg::Buy(BuyParams {
+            initiator: Some(Addr::unchecked(GRANTER)),
+            amounts: vec![PoolAmount {
+                pool_id: 0,
+                amount: Uint128::new(amount),
+            }],
+            deadline: None,
+            valid_until_height: None,
+            use_internal_balance: None,
+        })
+    }
+
+    #[test]
+    fn grants_count_spending_against_their_cap() {
+        let mut deps = setup();
+        grant(&mut deps, None, Some(2_000_000));
+
+        exec(&mut deps, GRANTEE, 1_500_000, granted_buy_msg(1_500_000)).unwrap();
+        assert!(!balance_of(&deps, GRANTER, 0).is_zero());
+
+        let err = exec(&mut deps, GRANTEE, 1_000_000, granted_buy_msg(1_000_000));
+        assert!(
+            matches!(err, Err(ContractError::NotAuthorized { msg }) if msg.contains("cap exceeded"))
+        );
+
+        exec(&mut deps, GRANTEE, 500_000, granted_buy_msg(500_000)).unwrap();
+        let msg = QueryMsg::Grants {
+            granter: Addr::unchecked(GRANTER),
+        };
+        let resp: GrantsResponse =
+            from_json(query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        assert_eq!(resp.grants[0].grant.quote_spent, Uint128::new(2_000_000));
+    }
+
+    #[test]
+    fn grants_expire_and_can_be_revoked() {
+        let mut deps = setup();
+        let expires = mock_env().block.time.plus_seconds(60);
+        grant(&mut deps, Some(expires), None);
+
+        let mut env = mock_env();
+        env.block.time = expires;
+        let err = exec_at(
+            &mut deps,
+            env,
+            GRANTEE,
+            1_000_000,
+            granted_buy_msg(1_000_000),
+        );
+        assert!(matches!(err, Err(ContractError::NotAuthorized { msg }) if msg == "grant expired"));
+
+        exec(&mut deps, GRANTEE, 1_000_000, granted_buy_msg(1_000_000)).unwrap();
+
+        let msg = ExecuteMsg::Revoke {
+            grantee: Addr::unchecked(GRANTEE),
+        };
+        exec(&mut deps, GRANTER, 0, msg).unwrap();
+        let err = exec(&mut deps, GRANTEE, 1_000_000, granted_buy_msg(1_000_000));
+        assert!(
+            matches!(err, Err(ContractError::NotAuthorized { msg }) if msg.contains("has no grant"))
+        );
+    }
+
+    #[test]
+    fn grants_reject_other_senders_and_actions() {
+        let mut deps = setup();
+        grant(&mut deps, None, None);
+
+        let err = exec(&mut deps, "stranger", 1_000_000, granted_buy_msg(1_000_000));
+        assert!(
+            matches!(err, Err(ContractError::NotAuthorized { msg }) if msg.contains("has no grant"))
+        );
+
+        // The grant only allows buys
+        exec(&mut deps, GRANTER, 1_000_000, buy_msg(0, 1_000_000)).unwrap();
+        let msg = ExecuteMsg::Sell(SellParams {
+            initiator: Some(Addr::unchecked(GRANTER)),
+            amounts: vec![PoolAmount {
+                pool_id: 0,
+                amount: balance_of(&deps, GRANTER, 0),
+            }],
+            deadline: None,
+            valid_until_height: None,
+            use_internal_balance: None,
+        });
+        let err = exec(&mut deps, GRANTEE, 0, msg);
+        assert!(
+            matches!(err, Err(ContractError::NotAuthorized { msg }) if msg.contains("does not allow"))
+        );
+    }
+
+    fn batch_msg(
+        initiator: Option<&str>,
+        ops: Vec<TradeOp>,
+    ) -> ExecuteMsg {
+        ExecuteMsg::Batch(BatchParams {
+            initiator: initiator.map(Addr::unchecked),
+            ops,
+            min_final_balances: vec![],
+            deadline: None,
+            valid_until_height: None,
+            use_internal_balance: None,
+        })
+    }
+
+    /// Quote sent to each recipient by the response's bank transfers.
+    fn transfers(resp: &Response) -> Vec<(String, u128)> {
+        resp.messages
+            .iter()
+            .filter_map(|sub_msg| match &sub_msg.msg {
+                CosmosMsg::Bank(BankMsg::Send { to_address, amount }) => {
+                    Some((to_address.clone(), amount[0].amount.u128()))
+                },
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn batches_net_to_one_payout_and_one_fee_transfer() {
+        let mut deps = setup();
+        buy(&mut deps, 0, 2_000_000);
+        let ops = vec![
+            TradeOp::Sell {
+                pool_id: 0,
+                amount: balance(&deps, 0),
+            },
+            TradeOp::Buy {
+                pool_id: 1,
+                amount: Uint128::new(1_000_000),
+            },
+        ];
+        let resp = exec(&mut deps, TRADER, 500_000, batch_msg(None, ops)).unwrap();
+
+        let transfers = transfers(&resp);
+        assert_eq!(transfers.len(), 2);
+        assert_eq!(transfers.iter().filter(|(to, _)| to == TRADER).count(), 1);
+        assert_eq!(transfers.iter().filter(|(to, _)| to == "admin").count(), 1);
+        assert!(balance(&deps, 0).is_zero());
+        assert!(!balance(&deps, 1).is_zero());
+    }
+
+    #[test]
+    fn batches_revert_entirely_when_a_step_fails() {
+        let mut deps = setup();
+        buy(&mut deps, 0, 1_000_000);
+        let before = query_trader(&deps);
+
+        let ops = vec![
+            TradeOp::Buy {
+                pool_id: 0,
+                amount: Uint128::new(1_000_000),
+            },
+            TradeOp::Sell {
+                pool_id: 1,
+                amount: Uint128::new(1_000_000),
+            },
+        ];
+        assert!(exec(&mut deps, TRADER, 1_000_000, batch_msg(None, ops)).is_err());
+        assert_eq!(query_trader(&deps), before);
+    }
+
+    #[test]
+    fn batches_refund_unused_funds_to_the_sender() {
+        let mut deps = setup();
+        grant(&mut deps, None, None);
+        let ops = vec![TradeOp::Buy {
+            pool_id: 0,
+            amount: Uint128::new(1_000_000),
+        }];
+        let resp = exec(&mut deps, GRANTEE, 1_500_000, batch_msg(Some(GRANTER), ops)).unwrap();
+
+        assert_eq!(
+            transfers(&resp),
+            vec![(GRANTEE.to_owned(), 500_000), ("admin".to_owned(), 10_000)]
+        );
+        assert!(!balance_of(&deps, GRANTER, 0).is_zero());
+    }
+
+    #[test]
+    fn internal_balances_fund_buys_and_can_be_withdrawn() {
+        let mut deps = setup();
+        exec(&mut deps, TRADER, 5_000_000, ExecuteMsg::Deposit {}).unwrap();
+
+        let msg = ExecuteMsg::Buy(BuyParams {
+            initiator: None,
+            amounts: vec![PoolAmount {
+                pool_id: 0,
+                amount: Uint128::new(1_000_000),
+            }],
+            deadline: None,
+            valid_until_height: None,
+            use_internal_balance: Some(true),
+        });
+        exec(&mut deps, TRADER, 0, msg).unwrap();
+        assert_eq!(query_trader(&deps).quote_balance, Uint128::new(4_000_000));
+        assert!(!balance(&deps, 0).is_zero());
+
+        // The contract holds the deposit less the buy fee it forwarded
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, coins(4_990_000, DENOM));
+
+        let err = exec(
+            &mut deps,
+            TRADER,
+            0,
+            ExecuteMsg::Withdraw {
+                amount: Uint128::new(4_000_001),
+            },
+        );
+        assert!(matches!(
+            err,
+            Err(ContractError::InsufficientBalance { .. })
+        ));
+
+        let resp = exec(
+            &mut deps,
+            TRADER,
+            0,
+            ExecuteMsg::Withdraw {
+                amount: Uint128::new(4_000_000),
+            },
+        )
+        .unwrap();
+        assert_eq!(transfers(&resp), vec![(TRADER.to_owned(), 4_000_000)]);
+        assert!(query_trader(&deps).quote_balance.is_zero());
+    }
+
+    #[test]
+    fn withdrawals_may_not_leave_claims_uncovered() {
+        let mut deps = setup();
+        exec(&mut deps, TRADER, 5_000_000, ExecuteMsg::Deposit {}).unwrap();
+        buy(&mut deps, 0, 1_000_000);
+
+        // The contract holds less than the deposit plus the pot it owes winners
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, coins(5_500_000, DENOM));
+
+        let err = exec(
+            &mut deps,
+            TRADER,
+            0,
+            ExecuteMsg::Withdraw {
+                amount: Uint128::new(5_000_000),
+            },
+        );
+        assert!(matches!(err, Err(ContractError::InsufficientFunds { .. })));
+        assert_eq!(query_trader(&deps).quote_balance, Uint128::new(5_000_000));
+
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, coins(5_990_000, DENOM));
+        exec(
+            &mut deps,
+            TRADER,
+            0,
+            ExecuteMsg::Withdraw {
+                amount: Uint128::new(5_000_000),
+            },
+        )
+        .unwrap();
+    }
+}
@@ -1,4 +1,5 @@
 use cosmwasm_std::StdError;
+use cw_utils::PaymentError;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -6,6 +7,9 @@ pub enum ContractError {
     #[error("{0}")]
     Std(#[from] StdError),
 
+    #[error("{0}")]
+    Payment(#[from] PaymentError),
+
     #[error("InvalidReplyId: {id:?}")]
     InvalidReplyId { id: u64 },
 
@@ -26,6 +30,12 @@ pub enum ContractError {
 
     #[error("InsufficientBalance: {msg:?}")]
     InsufficientBalance { msg: String },
+
+    #[error("Paused: {msg:?}")]
+    Paused { msg: String },
+
+    #[error("Expired: {msg:?}")]
+    Expired { msg: String },
 }
 
 impl From<ContractError> for StdError {
@@ -0,0 +1,248 @@
+use crate::{
+    error::ContractError,
+    math::{add_u128, mul_ratio_u128, sub_u128},
+    msg::{AgainstParams, PoolAmount, TradeKind},
+    state::{
+        models::AgainstPosition,
+        storage::{AGAINST_POSITIONS, BUY_FEE_PCT, FEE_MANAGER_ADDR, QUOTE_TOKEN, SELL_FEE_PCT},
+        utils::{ensure_min_trade_size, ensure_not_expired, resolve_initiator},
+    },
+};
+use cosmwasm_std::{attr, Response, Uint128};
+
+use super::{
+    buy::{buy_pool, settle_trader_buy},
+    collect_quote, pay_quote,
+    sell::{sell_pool, settle_trader_sell},
+    Context, Fill,
+};
+
+/// Buy a position against a pool by buying into every other pool in
+/// proportion to their current prices.
+pub fn exec_buy_against(
+    ctx: Context,
+    params: AgainstParams,
+) -> Result<Response, ContractError> {
+    let Context { deps, info, env } = ctx;
+    let AgainstParams {
+        initiator,
+        pool_id,
+        amount,
+        deadline,
+        valid_until_height,
+        use_internal_balance,
+    } = params;
+    let use_internal_balance = use_internal_balance.unwrap_or(false);
+
+    ensure_not_expired(&env.block, deadline, valid_until_height)?;
+
+    if amount.is_zero() {
+        return Err(ContractError::ValidationError {
+            msg: "cannot buy 0 amount".to_owned(),
+        });
+    }
+
+    ensure_min_trade_size(deps.storage, TradeKind::Buy, amount)?;
+
+    let buyer = resolve_initiator(
+        deps.storage,
+        deps.api,
+        env.block.time,
+        &info.sender,
+        initiator,
+        TradeKind::Buy,
+        amount,
+    )?;
+
+    let quote_token = QUOTE_TOKEN.load(deps.storage)?;
+    let refund = collect_quote(
+        deps.storage,
+        &info,
+        &quote_token,
+        &buyer,
+        amount,
+        use_internal_balance,
+    )?;
+
+    let fee_pct = BUY_FEE_PCT.load(deps.storage)?;
+    let mut total_fee_amount = Uint128::zero();
+    let mut total_in_amount = Uint128::zero();
+    let mut position = AGAINST_POSITIONS
+        .may_load(deps.storage, (&buyer, pool_id))?
+        .unwrap_or_else(|| AgainstPosition {
+            shares: Uint128::zero(),
+            legs: vec![],
+        });
+
+    for allocation in AgainstPosition::allocate(deps.storage, pool_id, amount)? {
+        // Pools priced too low to be allocated any quote are skipped
+        if allocation.amount.is_zero() {
+            continue;
+        }
+
+        let Fill {
+            in_amount,
+            out_amount,
+            fee_amount,
+        } = buy_pool(
+            deps.storage,
+            env.block.time,
+            &buyer,
+            allocation.pool_id,
+            allocation.amount,
+            fee_pct,
+        )?;
+
+        total_fee_amount = add_u128(total_fee_amount, fee_amount)?;
+        total_in_amount = add_u128(total_in_amount, in_amount)?;
+
+        if let Some(leg) = position
+            .legs
+            .iter_mut()
+            .find(|leg| leg.pool_id == allocation.pool_id)
+        {
+            leg.amount = add_u128(leg.amount, out_amount)?;
+        } else {
+            position.legs.push(PoolAmount {
+                pool_id: allocation.pool_id,
+                amount: out_amount,
+            });
+        }
+    }
+
+    position.shares = add_u128(position.shares, amount)?;
+    AGAINST_POSITIONS.save(deps.storage, (&buyer, pool_id), &position)?;
+
+    settle_trader_buy(deps.storage, &buyer, total_in_amount)?;
+
+    let mut resp = Response::new().add_submessages(refund);
+
+    if !total_fee_amount.is_zero() {
+        resp = resp.add_submessage(
+            quote_token.transfer(&FEE_MANAGER_ADDR.load(deps.storage)?, total_fee_amount)?,
+        );
+    }
+
+    Ok(resp.add_attributes(vec![
+        attr("action", "buy_against"),
+        attr("pool_id", pool_id.to_string()),
+        attr("fee_amount", total_fee_amount.u128().to_string()),
+        attr("in_amount", total_in_amount.u128().to_string()),
+        attr("shares", amount.u128().to_string()),
+    ]))
+}
+
+/// Unwind shares of a position against a pool by selling the same fraction of
+/// each of its legs.
+pub fn exec_sell_against(
+    ctx: Context,
+    params: AgainstParams,
+) -> Result<Response, ContractError> {
+    let Context { deps, info, env } = ctx;
+    let AgainstParams {
+        initiator,
+        pool_id,
+        amount,
+        deadline,
+        valid_until_height,
+        use_internal_balance,
+    } = params;
+    let use_internal_balance = use_internal_balance.unwrap_or(false);
+
+    ensure_not_expired(&env.block, deadline, valid_until_height)?;
+
+    let seller = resolve_initiator(
+        deps.storage,
+        deps.api,
+        env.block.time,
+        &info.sender,
+        initiator,
+        TradeKind::Sell,
+        Uint128::zero(),
+    )?;
+
+    let mut position = AGAINST_POSITIONS
+        .may_load(deps.storage, (&seller, pool_id))?
+        .ok_or_else(|| ContractError::NotAuthorized {
+            msg: format!("no position against pool {}", pool_id),
+        })?;
+
+    if amount.is_zero() || amount > position.shares {
+        return Err(ContractError::InsufficientBalance {
+            msg: format!("position has {} shares", position.shares),
+        });
+    }
+
+    // Take the sold fraction of each leg out of the position before selling,
+    // so that the sells only clamp legs of the trader's other positions.
+    let mut sells: Vec<PoolAmount> = Vec::with_capacity(position.legs.len());
+
+    for leg in position.legs.iter_mut() {
+        let sell_amount = if amount == position.shares {
+            leg.amount
+        } else {
+            mul_ratio_u128(leg.amount, amount, position.shares)?
+        };
+
+        if sell_amount.is_zero() {
+            continue;
+        }
+
+        leg.amount = sub_u128(leg.amount, sell_amount)?;
+        sells.push(PoolAmount {
+            pool_id: leg.pool_id,
+            amount: sell_amount,
+        });
+    }
+
+    position.shares = sub_u128(position.shares, amount)?;
+    if position.shares.is_zero() {
+        AGAINST_POSITIONS.remove(deps.storage, (&seller, pool_id));
+    } else {
+        AGAINST_POSITIONS.save(deps.storage, (&seller, pool_id), &position)?;
+    }
+
+    let fee_pct = SELL_FEE_PCT.load(deps.storage)?;
+    let mut total_out_amount = Uint128::zero();
+    let mut total_fee_amount = Uint128::zero();
+
+    for sell in sells {
+        let Fill {
+            out_amount,
+            fee_amount,
+            ..
+        } = sell_pool(
+            deps.storage,
+            env.block.time,
+            &seller,
+            sell.pool_id,
+            sell.amount,
+            fee_pct,
+        )?;
+
+        total_out_amount = add_u128(total_out_amount, out_amount)?;
+        total_fee_amount = add_u128(total_fee_amount, fee_amount)?;
+    }
+
+    let total_out_amount_post_fee = sub_u128(total_out_amount, total_fee_amount)?;
+
+    settle_trader_sell(deps.storage, &seller, total_out_amount_post_fee)?;
+
+    let quote_token = QUOTE_TOKEN.load(deps.storage)?;
+
+    Ok(Response::new()
+        .add_submessages(pay_quote(
+            deps.storage,
+            &quote_token,
+            &seller,
+            total_out_amount_post_fee,
+            use_internal_balance,
+        )?)
+        .add_attributes(vec![
+            attr("action", "sell_against"),
+            attr("pool_id", pool_id.to_string()),
+            attr("fee_amount", total_fee_amount.u128().to_string()),
+            attr("out_amount", total_out_amount.u128().to_string()),
+            attr("shares", amount.u128().to_string()),
+        ]))
+}
@@ -0,0 +1,218 @@
+use crate::{
+    error::ContractError,
+    math::{add_u128, sub_u128},
+    msg::{BatchParams, PoolAmount, TradeKind, TradeOp},
+    state::{
+        storage::{BUY_FEE_PCT, FEE_MANAGER_ADDR, POOL_ACCOUNTS, QUOTE_TOKEN, SELL_FEE_PCT},
+        utils::{ensure_min_trade_size, ensure_not_expired, resolve_initiator},
+    },
+};
+use cosmwasm_std::{attr, Response, Uint128};
+use cw_utils::{may_pay, nonpayable};
+
+use super::{
+    buy::{buy_pool, settle_trader_buy},
+    debit_internal_quote, pay_quote,
+    sell::{sell_pool, settle_trader_sell},
+    swap::{swap_pools, SwapOutcome},
+    Context, Fill,
+};
+
+pub fn exec_batch(
+    ctx: Context,
+    params: BatchParams,
+) -> Result<Response, ContractError> {
+    let Context { deps, info, env } = ctx;
+    let BatchParams {
+        initiator,
+        ops,
+        min_final_balances,
+        deadline,
+        valid_until_height,
+        use_internal_balance,
+    } = params;
+    let use_internal_balance = use_internal_balance.unwrap_or(false);
+
+    if ops.is_empty() {
+        return Err(ContractError::ValidationError {
+            msg: "batch must contain at least one op".to_owned(),
+        });
+    }
+
+    ensure_not_expired(&env.block, deadline, valid_until_height)?;
+
+    let quote_token = QUOTE_TOKEN.load(deps.storage)?;
+    let buy_fee_pct = BUY_FEE_PCT.load(deps.storage)?;
+    let sell_fee_pct = SELL_FEE_PCT.load(deps.storage)?;
+
+    // Authorize the initiator for each kind of trade present in the batch,
+    // counting the total buy amount against any grant's spending cap.
+    let mut total_buy_amount = Uint128::zero();
+    let mut kinds: Vec<TradeKind> = Vec::with_capacity(3);
+    for op in ops.iter() {
+        let kind = match op {
+            TradeOp::Buy { amount, .. } => {
+                total_buy_amount = add_u128(total_buy_amount, *amount)?;
+                TradeKind::Buy
+            },
+            TradeOp::Sell { .. } => TradeKind::Sell,
+            TradeOp::Swap { .. } => TradeKind::Swap,
+        };
+        if !kinds.contains(&kind) {
+            kinds.push(kind);
+        }
+    }
+
+    let mut trader = info.sender.clone();
+    for kind in kinds {
+        trader = resolve_initiator(
+            deps.storage,
+            deps.api,
+            env.block.time,
+            &info.sender,
+            initiator.clone(),
+            kind,
+            if let TradeKind::Buy = kind {
+                total_buy_amount
+            } else {
+                Uint128::zero()
+            },
+        )?;
+    }
+
+    // Running totals of quote flowing into and out of the contract. Buy and
+    // swap fees are forwarded to the fee manager; sell fees are retained.
+    let mut total_quote_in = Uint128::zero();
+    let mut total_quote_out = Uint128::zero();
+    let mut total_fee_amount = Uint128::zero();
+    let mut total_buy_in_amount = Uint128::zero();
+
+    for op in ops.iter() {
+        match *op {
+            TradeOp::Buy { pool_id, amount } => {
+                ensure_min_trade_size(deps.storage, TradeKind::Buy, amount)?;
+                let Fill {
+                    in_amount,
+                    fee_amount,
+                    ..
+                } = buy_pool(
+                    deps.storage,
+                    env.block.time,
+                    &trader,
+                    pool_id,
+                    amount,
+                    buy_fee_pct,
+                )?;
+
+                total_quote_in = add_u128(total_quote_in, amount)?;
+                total_fee_amount = add_u128(total_fee_amount, fee_amount)?;
+                total_buy_in_amount = add_u128(total_buy_in_amount, in_amount)?;
+            },
+            TradeOp::Sell { pool_id, amount } => {
+                ensure_min_trade_size(deps.storage, TradeKind::Sell, amount)?;
+                let Fill {
+                    out_amount,
+                    fee_amount,
+                    ..
+                } = sell_pool(
+                    deps.storage,
+                    env.block.time,
+                    &trader,
+                    pool_id,
+                    amount,
+                    sell_fee_pct,
+                )?;
+
+                total_quote_out = add_u128(total_quote_out, sub_u128(out_amount, fee_amount)?)?;
+            },
+            TradeOp::Swap {
+                from_pool,
+                to_pool,
+                amount,
+            } => {
+                let SwapOutcome { fee_amount, .. } = swap_pools(
+                    deps.storage,
+                    env.block.time,
+                    &trader,
+                    from_pool,
+                    to_pool,
+                    amount,
+                )?;
+                total_fee_amount = add_u128(total_fee_amount, fee_amount)?;
+            },
+        }
+    }
+
+    if !total_buy_in_amount.is_zero() {
+        settle_trader_buy(deps.storage, &trader, total_buy_in_amount)?;
+    }
+
+    if !total_quote_out.is_zero() {
+        settle_trader_sell(deps.storage, &trader, total_quote_out)?;
+    }
+
+    // Ensure the trader ends up with at least the required balances
+    for PoolAmount { pool_id, amount } in min_final_balances.iter() {
+        let balance = POOL_ACCOUNTS
+            .may_load(deps.storage, (&trader, *pool_id))?
+            .map(|account| account.balance)
+            .unwrap_or_default();
+        if balance < *amount {
+            return Err(ContractError::ValidationError {
+                msg: format!("final balance in pool {} below minimum", pool_id),
+            });
+        }
+    }
+
+    // Attached funds must cover buys in excess of sell proceeds. Net proceeds
+    // are paid to the trader, while unused funds are refunded to the sender,
+    // in a single transfer when they are the same account.
+    let shortfall_amount = total_quote_in.saturating_sub(total_quote_out);
+    let mut payout_amount = total_quote_out.saturating_sub(total_quote_in);
+    let funds_amount = if use_internal_balance {
+        debit_internal_quote(deps.storage, &info, &trader, shortfall_amount)?;
+        shortfall_amount
+    } else if let Some(denom) = quote_token.get_denom() {
+        may_pay(&info, &denom)?
+    } else {
+        nonpayable(&info)?;
+        Uint128::zero()
+    };
+    if funds_amount < shortfall_amount {
+        return Err(ContractError::InsufficientFunds {
+            msg: format!("batch requires {} quote", shortfall_amount),
+        });
+    }
+
+    let mut refund_amount = sub_u128(funds_amount, shortfall_amount)?;
+    if trader == info.sender {
+        payout_amount = add_u128(payout_amount, refund_amount)?;
+        refund_amount = Uint128::zero();
+    }
+
+    let mut resp = Response::new().add_submessages(pay_quote(
+        deps.storage,
+        &quote_token,
+        &trader,
+        payout_amount,
+        use_internal_balance,
+    )?);
+
+    if !refund_amount.is_zero() {
+        resp = resp.add_submessage(quote_token.transfer(&info.sender, refund_amount)?);
+    }
+
+    if !total_fee_amount.is_zero() {
+        resp = resp.add_submessage(
+            quote_token.transfer(&FEE_MANAGER_ADDR.load(deps.storage)?, total_fee_amount)?,
+        );
+    }
+
+    Ok(resp.add_attributes(vec![
+        attr("action", "batch"),
+        attr("num_ops", ops.len().to_string()),
+        attr("fee_amount", total_fee_amount.u128().to_string()),
+        attr("in_amount", total_quote_in.u128().to_string()),
+        attr("out_amount", payout_amount.u128().to_string()),
+    ]))
+}
@@ -1,30 +1,41 @@
 use crate::{
     error::ContractError,
     math::{add_u128, add_u256, add_u32, mul_pct_u128, sub_u128},
-    msg::{BuyParams, PoolAmount},
+    msg::{BuyParams, PoolAmount, TradeAction, TradeKind},
     state::{
-        models::{OhlcBar, Pool, PoolAccount, TraderInfo, TraderStats},
+        models::{
+            OhlcBar, Pool, PoolAccount, PositionCost, ProbabilitySnapshot, TradeRecord, TraderInfo,
+        },
         storage::{
-            BUY_FEE_PCT, FEE_MANAGER_ADDR, MARKET_STATS, POOLS, POOL_STATS, QUOTE_DECIMALS,
-            QUOTE_TOKEN, TRADER_INFOS,
+            PoolId, BUY_FEE_PCT, FEE_MANAGER_ADDR, MARKET_STATS, POOL_STATS, QUOTE_DECIMALS,
+            QUOTE_TOKEN,
+        },
+        utils::{
+            ensure_min_trade_size, ensure_not_expired, ensure_pool_not_paused, resolve_initiator,
         },
-        utils::resolve_initiator,
     },
 };
-use cosmwasm_std::{attr, Response, Uint128};
+use cosmwasm_std::{attr, Addr, Response, Storage, Timestamp, Uint128};
 
-use super::Context;
+use super::{collect_quote, Context, Fill};
 
 pub fn exec_buy(
     ctx: Context,
     params: BuyParams,
 ) -> Result<Response, ContractError> {
     let Context { deps, info, env } = ctx;
-    let BuyParams { amounts, initiator } = params;
+    let BuyParams {
+        amounts,
+        initiator,
+        deadline,
+        valid_until_height,
+        use_internal_balance,
+    } = params;
+    let use_internal_balance = use_internal_balance.unwrap_or(false);
+
+    ensure_not_expired(&env.block, deadline, valid_until_height)?;
     let quote_token = QUOTE_TOKEN.load(deps.storage)?;
-    let quote_decimals = QUOTE_DECIMALS.load(deps.storage)?;
     let fee_pct = BUY_FEE_PCT.load(deps.storage)?;
-    let buyer = resolve_initiator(deps.storage, deps.api, &info.sender, initiator)?;
 
     // Total quote amount swapping in
     let total_in_amount: Uint128 = amounts
@@ -39,13 +50,28 @@ pub fn exec_buy(
         });
     }
 
-    if !quote_token.has_in_funds(&info.funds, Some(total_in_amount)) {
-        return Err(ContractError::InsufficientFunds {
-            msg: "insufficient funds".to_owned(),
-        });
-    }
+    let buyer = resolve_initiator(
+        deps.storage,
+        deps.api,
+        env.block.time,
+        &info.sender,
+        initiator,
+        TradeKind::Buy,
+        total_in_amount,
+    )?;
+
+    let refund = collect_quote(
+        deps.storage,
+        &info,
+        &quote_token,
+        &buyer,
+        total_in_amount,
+        use_internal_balance,
+    )?;
 
-    let mut resp = Response::new().add_attribute("action", "buy");
+    let mut resp = Response::new()
+        .add_attribute("action", "buy")
+        .add_submessages(refund);
     let mut total_fee_amount = Uint128::zero();
     let mut total_in_amount = Uint128::zero();
     let mut total_out_amount = Uint128::zero();
@@ -54,87 +80,35 @@ pub fn exec_buy(
         let pool_id = *pool_id;
         let amount = *amount;
 
-        let mut pool = Pool::load(deps.storage, pool_id)?;
-        let fee_amount = mul_pct_u128(amount, fee_pct)?;
-        let in_amount_post_fee = sub_u128(amount, fee_amount)?;
+        ensure_min_trade_size(deps.storage, TradeKind::Buy, amount)?;
 
-        // Swap in quote token
-        let out_amount = pool.buy(in_amount_post_fee)?;
-
-        // Update or create sender's account for specifically this pool
-        let account = PoolAccount::upsert(deps.storage, &buyer, pool_id, out_amount, true)?;
+        let Fill {
+            in_amount: in_amount_post_fee,
+            out_amount,
+            fee_amount,
+        } = buy_pool(
+            deps.storage,
+            env.block.time,
+            &buyer,
+            pool_id,
+            amount,
+            fee_pct,
+        )?;
 
         // Agg running totals
         total_fee_amount = add_u128(total_fee_amount, fee_amount)?;
         total_in_amount = add_u128(total_in_amount, in_amount_post_fee)?;
         total_out_amount = add_u128(total_out_amount, out_amount)?;
+    }
 
-        POOLS.save(deps.storage, pool_id, &pool)?;
-
-        let price = pool.calc_quote_price(quote_decimals)?;
-
-        // Update statistics pertaining specifically to this pool
-        POOL_STATS.update(
-            deps.storage,
-            pool_id,
-            |maybe_stats| -> Result<_, ContractError> {
-                if let Some(mut stats) = maybe_stats {
-                    stats.quote_amount_in = add_u256(stats.quote_amount_in, in_amount_post_fee)?;
-                    stats.base_amount_out = add_u256(stats.base_amount_out, out_amount)?;
-                    stats.fees_collected = add_u128(stats.fees_collected, fee_amount)?;
-                    stats.num_buys = add_u32(stats.num_buys, 1)?;
-                    if account.balance == out_amount {
-                        stats.num_traders = add_u32(stats.num_traders, 1)?;
-                    }
-                    Ok(stats)
-                } else {
-                    return Err(ContractError::NotAuthorized {
-                        msg: format!("could not load stats for pool {}", pool_id),
-                    });
-                }
-            },
-        )?;
-
-        // Update or add a historical trading OHLC "candlestick"
-        OhlcBar::upsert(deps.storage, pool_id, env.block.time, price, out_amount)?;
-
-        // Add submsg to transfer fee to fee manager account
+    // Transfer the fees from all pools to the fee manager account at once
+    if !total_fee_amount.is_zero() {
         resp = resp.add_submessage(
-            quote_token.transfer(&FEE_MANAGER_ADDR.load(deps.storage)?, fee_amount)?,
-        )
+            quote_token.transfer(&FEE_MANAGER_ADDR.load(deps.storage)?, total_fee_amount)?,
+        );
     }
 
-    // Upsert a TraderInfo for tx sender
-    let trader = TRADER_INFOS.update(
-        deps.storage,
-        &buyer,
-        |maybe_trader_info| -> Result<_, ContractError> {
-            if let Some(mut trader_info) = maybe_trader_info {
-                trader_info.stats.num_buys = add_u32(trader_info.stats.num_buys, 1)?;
-                trader_info.stats.quote_amount_in =
-                    add_u128(trader_info.stats.quote_amount_in, total_in_amount)?;
-                Ok(trader_info)
-            } else {
-                Ok(TraderInfo {
-                    stats: TraderStats {
-                        amount_claimed: Uint128::zero(),
-                        quote_amount_out: Uint128::zero(),
-                        quote_amount_in: total_in_amount,
-                        num_sells: 0,
-                        num_buys: 1,
-                    },
-                })
-            }
-        },
-    )?;
-
-    // Increment global trader count if this is a new account
-    if trader.stats.num_buys == 1 {
-        MARKET_STATS.update(deps.storage, |mut stats| -> Result<_, ContractError> {
-            stats.num_traders = add_u32(stats.num_traders, 1)?;
-            Ok(stats)
-        })?;
-    }
+    settle_trader_buy(deps.storage, &buyer, total_in_amount)?;
 
     Ok(resp.add_attributes(vec![
         attr("fee_amount", total_fee_amount.u128().to_string()),
@@ -142,3 +116,111 @@ pub fn exec_buy(
         attr("out_amount", total_out_amount.u128().to_string()),
     ]))
 }
+
+/// Swap amount of quote (including fees) into a pool for the buyer.
+pub fn buy_pool(
+    store: &mut dyn Storage,
+    time: Timestamp,
+    buyer: &Addr,
+    pool_id: PoolId,
+    amount: Uint128,
+    fee_pct: Uint128,
+) -> Result<Fill, ContractError> {
+    ensure_pool_not_paused(store, pool_id)?;
+
+    let mut pool = Pool::load(store, pool_id)?;
+    let fee_amount = mul_pct_u128(amount, fee_pct)?;
+    let in_amount = sub_u128(amount, fee_amount)?;
+
+    // Swap in quote token
+    let out_amount = pool.buy(in_amount)?;
+
+    let fill = Fill {
+        in_amount,
+        out_amount,
+        fee_amount,
+    };
+
+    settle_buy(store, time, buyer, pool_id, &pool, &fill)?;
+
+    Ok(fill)
+}
+
+/// Persist a pool after a buy and record the fill in the buyer's pool account,
+/// the pool's stats and its OHLC bars.
+pub fn settle_buy(
+    store: &mut dyn Storage,
+    time: Timestamp,
+    buyer: &Addr,
+    pool_id: PoolId,
+    pool: &Pool,
+    fill: &Fill,
+) -> Result<(), ContractError> {
+    let Fill {
+        in_amount,
+        out_amount,
+        fee_amount,
+    } = *fill;
+
+    // Update or create buyer's account for specifically this pool
+    PoolAccount::upsert(store, buyer, pool_id, out_amount, true)?;
+    PositionCost::record_buy(store, buyer, pool_id, add_u128(in_amount, fee_amount)?)?;
+
+    pool.save(store, pool_id)?;
+
+    let price = pool.calc_quote_price(QUOTE_DECIMALS.load(store)?)?;
+
+    // Update statistics pertaining specifically to this pool
+    POOL_STATS.update(store, pool_id, |maybe_stats| -> Result<_, ContractError> {
+        if let Some(mut stats) = maybe_stats {
+            stats.quote_amount_in = add_u256(stats.quote_amount_in, in_amount)?;
+            stats.base_amount_out = add_u256(stats.base_amount_out, out_amount)?;
+            stats.fees_collected = add_u128(stats.fees_collected, fee_amount)?;
+            stats.num_buys = add_u32(stats.num_buys, 1)?;
+            Ok(stats)
+        } else {
+            Err(ContractError::NotAuthorized {
+                msg: format!("could not load stats for pool {}", pool_id),
+            })
+        }
+    })?;
+
+    // Update or add a historical trading OHLC "candlestick"
+    OhlcBar::upsert(store, pool_id, time, price, in_amount)?;
+    ProbabilitySnapshot::record(store, time)?;
+
+    TradeRecord {
+        action: TradeAction::Buy,
+        pool_ids: vec![pool_id],
+        prices: vec![price],
+        in_amount,
+        out_amount,
+        fee_amount,
+        t: time.seconds().into(),
+    }
+    .append(store, buyer)?;
+
+    Ok(())
+}
+
+/// Record a buy of in_amount of quote (net of fees) across one or more pools
+/// in the buyer's and the market's stats.
+pub fn settle_trader_buy(
+    store: &mut dyn Storage,
+    buyer: &Addr,
+    in_amount: Uint128,
+) -> Result<(), ContractError> {
+    TraderInfo::upsert(store, buyer, |stats| {
+        stats.num_buys = add_u32(stats.num_buys, 1)?;
+        stats.quote_amount_in = add_u128(stats.quote_amount_in, in_amount)?;
+        Ok(())
+    })?;
+
+    MARKET_STATS.update(store, |mut stats| -> Result<_, ContractError> {
+        stats.num_buys = add_u32(stats.num_buys, 1)?;
+        stats.quote_amount_in = add_u256(stats.quote_amount_in, in_amount)?;
+        Ok(stats)
+    })?;
+
+    Ok(())
+}
@@ -0,0 +1,123 @@
+use crate::{
+    error::ContractError,
+    math::{mul_ratio_ceil_u128, sub_u128},
+    msg::{BuyExactOutParams, TradeKind},
+    state::{
+        models::Pool,
+        storage::{BUY_FEE_PCT, FEE_MANAGER_ADDR, QUOTE_TOKEN},
+        utils::{
+            ensure_min_trade_size, ensure_not_expired, ensure_pool_not_paused, resolve_initiator,
+        },
+    },
+};
+use cosmwasm_std::{attr, Response, Uint128};
+
+use super::{
+    buy::{settle_buy, settle_trader_buy},
+    collect_quote, Context, Fill,
+};
+
+pub fn exec_buy_exact_out(
+    ctx: Context,
+    params: BuyExactOutParams,
+) -> Result<Response, ContractError> {
+    let Context { deps, info, env } = ctx;
+    let BuyExactOutParams {
+        initiator,
+        pool_id,
+        base_out,
+        max_quote_in,
+        deadline,
+        valid_until_height,
+        use_internal_balance,
+    } = params;
+    let use_internal_balance = use_internal_balance.unwrap_or(false);
+
+    ensure_not_expired(&env.block, deadline, valid_until_height)?;
+    let quote_token = QUOTE_TOKEN.load(deps.storage)?;
+    let fee_pct = BUY_FEE_PCT.load(deps.storage)?;
+
+    if base_out.is_zero() {
+        return Err(ContractError::ValidationError {
+            msg: "cannot buy 0 amount".to_owned(),
+        });
+    }
+
+    ensure_pool_not_paused(deps.storage, pool_id)?;
+
+    // Quote required by the curve, then grossed up so that the amount left
+    // after deducting the fee covers it.
+    let mut pool = Pool::load(deps.storage, pool_id)?;
+    let in_amount = pool.buy_exact_out(base_out)?;
+    let total_in_amount =
+        mul_ratio_ceil_u128(in_amount, 1_000_000u128, sub_u128(1_000_000u128, fee_pct)?)?;
+    let fee_amount = sub_u128(total_in_amount, in_amount)?;
+
+    ensure_min_trade_size(deps.storage, TradeKind::Buy, total_in_amount)?;
+
+    if total_in_amount > max_quote_in {
+        return Err(ContractError::ValidationError {
+            msg: format!(
+                "buy requires {} quote, exceeding max_quote_in",
+                total_in_amount
+            ),
+        });
+    }
+
+    let buyer = resolve_initiator(
+        deps.storage,
+        deps.api,
+        env.block.time,
+        &info.sender,
+        initiator,
+        TradeKind::Buy,
+        total_in_amount,
+    )?;
+
+    // Unused funds are refunded to the tx sender, who attached them
+    let refund = collect_quote(
+        deps.storage,
+        &info,
+        &quote_token,
+        &buyer,
+        total_in_amount,
+        use_internal_balance,
+    )?;
+    let refund_amount = if use_internal_balance {
+        Uint128::zero()
+    } else {
+        sub_u128(quote_token.amount_in_funds(&info.funds), total_in_amount)?
+    };
+
+    settle_buy(
+        deps.storage,
+        env.block.time,
+        &buyer,
+        pool_id,
+        &pool,
+        &Fill {
+            out_amount: base_out,
+            in_amount,
+            fee_amount,
+        },
+    )?;
+
+    settle_trader_buy(deps.storage, &buyer, in_amount)?;
+
+    let mut resp = Response::new().add_submessages(refund);
+
+    if !fee_amount.is_zero() {
+        resp = resp.add_submessage(
+            quote_token.transfer(&FEE_MANAGER_ADDR.load(deps.storage)?, fee_amount)?,
+        );
+    }
+
+    Ok(resp.add_attributes(vec![
+        attr("action", "buy_exact_out"),
+        attr("pool_id", pool_id.to_string()),
+        attr("fee_amount", fee_amount.u128().to_string()),
+        attr("in_amount", in_amount.u128().to_string()),
+        attr("out_amount", base_out.u128().to_string()),
+        attr("refund_amount", refund_amount.u128().to_string()),
+    ]))
+}
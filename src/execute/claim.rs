@@ -1,20 +1,21 @@
+use crate::msg::TradeAction;
+use crate::state::models::{Pool, TradeRecord, TraderInfo};
 use crate::state::storage::{
-    PoolId, AMOUNT_CLAIMED, HAS_CLAIMED, POOLS, POOL_ACCOUNTS, QUOTE_TOKEN, STOP_TIME, TRADER_INFOS,
+    AMOUNT_CLAIMED, FEE_MANAGER_ADDR, HAS_CLAIMED, MARKET_STATS, POOL_ACCOUNTS, QUOTE_DECIMALS,
+    QUOTE_TOKEN, STOP_TIME, WINNING_POOL_ID,
 };
+use crate::state::utils::{calc_claim, ensure_claims_not_paused, ensure_solvent};
 use crate::{
     error::ContractError,
-    math::{add_u128, mul_ratio_u128, sub_u128},
+    math::{add_u128, sub_u128},
 };
-use cosmwasm_std::{attr, Order, Response, Uint128};
+use cosmwasm_std::{attr, Response};
 
 use super::Context;
 
 pub fn exec_claim(ctx: Context) -> Result<Response, ContractError> {
     let Context { deps, info, env } = ctx;
 
-    // TODO: get this by querying the associated jury contract!
-    let winning_pool_id: PoolId = 0;
-
     // Ensure that the pool close time has been reached.
     if env.block.time <= STOP_TIME.load(deps.storage)? {
         return Err(ContractError::NotAuthorized {
@@ -22,6 +23,16 @@ pub fn exec_claim(ctx: Context) -> Result<Response, ContractError> {
         });
     }
 
+    ensure_claims_not_paused(deps.storage)?;
+
+    // Ensure that a resolver has declared the winning pool.
+    let winning_pool_id =
+        WINNING_POOL_ID
+            .may_load(deps.storage)?
+            .ok_or_else(|| ContractError::NotAuthorized {
+                msg: "the market has not been resolved".to_owned(),
+            })?;
+
     // Ensure the user has not already claimed.
     HAS_CLAIMED.update(
         deps.storage,
@@ -53,47 +64,59 @@ pub fn exec_claim(ctx: Context) -> Result<Response, ContractError> {
         });
     };
 
-    // Compute net_winnings and pool_balance.
-    let mut pool_balance = Uint128::zero(); // base balance of winning pool
-    let mut net_winnings = Uint128::zero(); // total quote balance across all pools
-
-    for result in POOLS.range(deps.storage, None, None, Order::Ascending) {
-        let (pool_id, pool) = result?;
-        net_winnings = add_u128(net_winnings, sub_u128(pool.reserves.quote, pool.offset)?)?;
-        if pool_id == winning_pool_id {
-            pool_balance = sub_u128(pool.supply, pool.reserves.base)?;
-        }
-    }
-
-    // Compute the account's claim amount.
+    // Compute the account's share of net winnings, less the claim fee.
     let quote_token = QUOTE_TOKEN.load(deps.storage)?;
-    let claim_amount = mul_ratio_u128(net_winnings, account.balance, pool_balance)?;
+    let (claim_amount, fee_amount) = calc_claim(deps.storage, winning_pool_id, account.balance)?;
+    let payout_amount = sub_u128(claim_amount, fee_amount)?;
 
-    // Increment the trader's running total amount claimed
-    TRADER_INFOS.update(
-        deps.storage,
-        &info.sender,
-        |maybe_info| -> Result<_, ContractError> {
-            if let Some(mut info) = maybe_info {
-                info.stats.amount_claimed = add_u128(info.stats.amount_claimed, claim_amount)?;
-                Ok(info)
-            } else {
-                Err(ContractError::NotAuthorized {
-                    msg: "No trader associated with sender".to_owned(),
-                })
-            }
-        },
-    )?;
+    // Increment the trader's and the market's running totals amount claimed
+    TraderInfo::upsert(deps.storage, &info.sender, |stats| {
+        stats.amount_claimed = add_u128(stats.amount_claimed, payout_amount)?;
+        Ok(())
+    })?;
+
+    MARKET_STATS.update(deps.storage, |mut stats| -> Result<_, ContractError> {
+        stats.amount_claimed = add_u128(stats.amount_claimed, payout_amount)?;
+        Ok(stats)
+    })?;
 
-    // Increment global amount claimed
+    // Increment global amount claimed, including the fee, which is paid out of
+    // the same winnings
     AMOUNT_CLAIMED.update(deps.storage, |n| -> Result<_, ContractError> {
         add_u128(n, claim_amount)
     })?;
 
-    Ok(Response::new()
-        .add_submessage(quote_token.transfer(&info.sender, claim_amount)?)
-        .add_attributes(vec![
-            attr("action", "claim"),
-            attr("claim_amount", claim_amount.u128().to_string()),
-        ]))
+    ensure_solvent(
+        deps.storage,
+        deps.querier,
+        &env.contract.address,
+        claim_amount,
+    )?;
+
+    let winning_pool = Pool::load(deps.storage, winning_pool_id)?;
+    TradeRecord {
+        action: TradeAction::Claim,
+        pool_ids: vec![winning_pool_id],
+        prices: vec![winning_pool.calc_quote_price(QUOTE_DECIMALS.load(deps.storage)?)?],
+        in_amount: account.balance,
+        out_amount: claim_amount,
+        fee_amount,
+        t: env.block.time.seconds().into(),
+    }
+    .append(deps.storage, &info.sender)?;
+
+    let mut resp =
+        Response::new().add_submessage(quote_token.transfer(&info.sender, payout_amount)?);
+
+    if !fee_amount.is_zero() {
+        resp = resp.add_submessage(
+            quote_token.transfer(&FEE_MANAGER_ADDR.load(deps.storage)?, fee_amount)?,
+        );
+    }
+
+    Ok(resp.add_attributes(vec![
+        attr("action", "claim"),
+        attr("claim_amount", payout_amount.u128().to_string()),
+        attr("fee_amount", fee_amount.u128().to_string()),
+    ]))
 }
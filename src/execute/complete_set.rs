@@ -0,0 +1,209 @@
+use crate::{
+    error::ContractError,
+    math::{add_u128, sub_u128},
+    msg::{CompleteSetParams, TradeAction, TradeKind},
+    state::{
+        models::{PoolAccount, PositionCost, TradeRecord, TraderInfo},
+        storage::{PoolId, POOLS, QUOTE_DECIMALS, QUOTE_TOKEN, SET_COLLATERAL, STOP_TIME},
+        utils::{allocate_by_price, ensure_not_expired, ensure_pool_not_paused, resolve_initiator},
+    },
+};
+use cosmwasm_std::{attr, Order, Response, Storage, Timestamp, Uint128};
+
+use super::{collect_quote, pay_quote, Context};
+
+/// Deposit amount of quote to receive amount units of position in every pool.
+pub fn exec_mint_set(
+    ctx: Context,
+    params: CompleteSetParams,
+) -> Result<Response, ContractError> {
+    let Context { deps, info, env } = ctx;
+    let CompleteSetParams {
+        initiator,
+        amount,
+        deadline,
+        valid_until_height,
+        use_internal_balance,
+    } = params;
+    let use_internal_balance = use_internal_balance.unwrap_or(false);
+
+    ensure_not_expired(&env.block, deadline, valid_until_height)?;
+    ensure_market_open(deps.storage, env.block.time)?;
+
+    if amount.is_zero() {
+        return Err(ContractError::ValidationError {
+            msg: "cannot mint 0 sets".to_owned(),
+        });
+    }
+
+    let minter = resolve_initiator(
+        deps.storage,
+        deps.api,
+        env.block.time,
+        &info.sender,
+        initiator,
+        TradeKind::Buy,
+        amount,
+    )?;
+
+    let quote_token = QUOTE_TOKEN.load(deps.storage)?;
+    let refund = collect_quote(
+        deps.storage,
+        &info,
+        &quote_token,
+        &minter,
+        amount,
+        use_internal_balance,
+    )?;
+
+    let pools = POOLS
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Attribute the cost of the set to each pool's position by price
+    let costs = allocate_by_price(deps.storage, amount, None)?;
+    let quote_decimals = QUOTE_DECIMALS.load(deps.storage)?;
+    let mut pool_ids: Vec<PoolId> = Vec::with_capacity(pools.len());
+    let mut prices: Vec<Uint128> = Vec::with_capacity(pools.len());
+
+    for ((pool_id, mut pool), cost) in pools.into_iter().zip(costs) {
+        ensure_pool_not_paused(deps.storage, pool_id)?;
+        pool.mint_set(amount)?;
+        pool.save(deps.storage, pool_id)?;
+        pool_ids.push(pool_id);
+        prices.push(pool.calc_quote_price(quote_decimals)?);
+        PoolAccount::upsert(deps.storage, &minter, pool_id, amount, true)?;
+        PositionCost::record_buy(deps.storage, &minter, pool_id, cost.amount)?;
+    }
+
+    SET_COLLATERAL.update(deps.storage, |n| -> Result<_, ContractError> {
+        add_u128(n, amount)
+    })?;
+
+    // Register the minter as a trader, since minting opens positions
+    TraderInfo::upsert(deps.storage, &minter, |_| Ok(()))?;
+
+    TradeRecord {
+        action: TradeAction::MintSet,
+        pool_ids,
+        prices,
+        in_amount: amount,
+        out_amount: amount,
+        fee_amount: Uint128::zero(),
+        t: env.block.time.seconds().into(),
+    }
+    .append(deps.storage, &minter)?;
+
+    Ok(Response::new().add_submessages(refund).add_attributes(vec![
+        attr("action", "mint_set"),
+        attr("amount", amount.u128().to_string()),
+    ]))
+}
+
+/// Return amount units of position in every pool for amount of quote.
+pub fn exec_redeem_set(
+    ctx: Context,
+    params: CompleteSetParams,
+) -> Result<Response, ContractError> {
+    let Context { deps, info, env } = ctx;
+    let CompleteSetParams {
+        initiator,
+        amount,
+        deadline,
+        valid_until_height,
+        use_internal_balance,
+    } = params;
+    let use_internal_balance = use_internal_balance.unwrap_or(false);
+
+    ensure_not_expired(&env.block, deadline, valid_until_height)?;
+    ensure_market_open(deps.storage, env.block.time)?;
+
+    if amount.is_zero() {
+        return Err(ContractError::ValidationError {
+            msg: "cannot redeem 0 sets".to_owned(),
+        });
+    }
+
+    let redeemer = resolve_initiator(
+        deps.storage,
+        deps.api,
+        env.block.time,
+        &info.sender,
+        initiator,
+        TradeKind::Sell,
+        Uint128::zero(),
+    )?;
+
+    let pools = POOLS
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Attribute the proceeds of the set to each pool's position by price
+    let proceeds = allocate_by_price(deps.storage, amount, None)?;
+    let quote_decimals = QUOTE_DECIMALS.load(deps.storage)?;
+    let mut pool_ids: Vec<PoolId> = Vec::with_capacity(pools.len());
+    let mut prices: Vec<Uint128> = Vec::with_capacity(pools.len());
+
+    for ((pool_id, mut pool), pool_proceeds) in pools.into_iter().zip(proceeds) {
+        ensure_pool_not_paused(deps.storage, pool_id)?;
+        pool.redeem_set(amount)?;
+        pool.save(deps.storage, pool_id)?;
+        pool_ids.push(pool_id);
+        prices.push(pool.calc_quote_price(quote_decimals)?);
+        PoolAccount::upsert(deps.storage, &redeemer, pool_id, amount, false)?;
+        PositionCost::record_sell(
+            deps.storage,
+            &redeemer,
+            pool_id,
+            amount,
+            pool_proceeds.amount,
+        )?;
+    }
+
+    // Redemptions are limited to the collateral deposited by minting
+    SET_COLLATERAL.update(deps.storage, |n| -> Result<_, ContractError> {
+        sub_u128(n, amount)
+    })?;
+
+    TraderInfo::upsert(deps.storage, &redeemer, |_| Ok(()))?;
+
+    TradeRecord {
+        action: TradeAction::RedeemSet,
+        pool_ids,
+        prices,
+        in_amount: amount,
+        out_amount: amount,
+        fee_amount: Uint128::zero(),
+        t: env.block.time.seconds().into(),
+    }
+    .append(deps.storage, &redeemer)?;
+
+    let quote_token = QUOTE_TOKEN.load(deps.storage)?;
+
+    Ok(Response::new()
+        .add_submessages(pay_quote(
+            deps.storage,
+            &quote_token,
+            &redeemer,
+            amount,
+            use_internal_balance,
+        )?)
+        .add_attributes(vec![
+            attr("action", "redeem_set"),
+            attr("amount", amount.u128().to_string()),
+        ]))
+}
+
+/// Complete sets change the pot and circulating supplies that claims are
+/// computed from, so they are only available while the market is open.
+fn ensure_market_open(
+    store: &dyn Storage,
+    time: Timestamp,
+) -> Result<(), ContractError> {
+    if time > STOP_TIME.load(store)? {
+        return Err(ContractError::NotAuthorized {
+            msg: "the market is closed".to_owned(),
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,35 @@
+use crate::{
+    error::ContractError,
+    state::{
+        models::{QuoteAccount, TraderInfo},
+        storage::QUOTE_TOKEN,
+    },
+};
+use cosmwasm_std::{attr, Response};
+use cw_utils::must_pay;
+
+use super::Context;
+
+/// Credit quote attached to the message to the sender's internal balance.
+pub fn exec_deposit(ctx: Context) -> Result<Response, ContractError> {
+    let Context { deps, info, .. } = ctx;
+
+    let denom = QUOTE_TOKEN.load(deps.storage)?.get_denom().ok_or_else(|| {
+        ContractError::NotImplemented {
+            msg: "quote token cannot be deposited with attached funds".to_owned(),
+        }
+    })?;
+
+    let amount = must_pay(&info, &denom)?;
+
+    let account = QuoteAccount::upsert(deps.storage, &info.sender, amount, true)?;
+
+    // Register the depositor as a trader so that their balance is queryable
+    TraderInfo::upsert(deps.storage, &info.sender, |_| Ok(()))?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "deposit"),
+        attr("amount", amount.u128().to_string()),
+        attr("balance", account.balance.u128().to_string()),
+    ]))
+}
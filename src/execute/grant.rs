@@ -0,0 +1,76 @@
+use crate::{
+    error::ContractError,
+    msg::TradeKind,
+    state::{models::TraderGrant, storage::TRADER_GRANTS},
+};
+use cosmwasm_std::{attr, Addr, Response, Timestamp, Uint128};
+
+use super::Context;
+
+pub fn exec_grant_trader(
+    ctx: Context,
+    grantee: Addr,
+    expires: Option<Timestamp>,
+    max_quote_spend: Option<Uint128>,
+    allowed_actions: Vec<TradeKind>,
+) -> Result<Response, ContractError> {
+    let Context { deps, info, env } = ctx;
+    let grantee = deps.api.addr_validate(grantee.as_str())?;
+
+    if grantee == info.sender {
+        return Err(ContractError::ValidationError {
+            msg: "cannot grant trading authority to self".to_owned(),
+        });
+    }
+
+    if allowed_actions.is_empty() {
+        return Err(ContractError::ValidationError {
+            msg: "grant must allow at least one action".to_owned(),
+        });
+    }
+
+    if expires.map(|t| t <= env.block.time).unwrap_or(false) {
+        return Err(ContractError::ValidationError {
+            msg: "grant expiration must be in the future".to_owned(),
+        });
+    }
+
+    // Replacing an existing grant resets its spending tally
+    TRADER_GRANTS.save(
+        deps.storage,
+        (&info.sender, &grantee),
+        &TraderGrant {
+            quote_spent: Uint128::zero(),
+            expires,
+            max_quote_spend,
+            allowed_actions,
+        },
+    )?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "grant_trader"),
+        attr("granter", info.sender.to_string()),
+        attr("grantee", grantee.to_string()),
+    ]))
+}
+
+pub fn exec_revoke(
+    ctx: Context,
+    grantee: Addr,
+) -> Result<Response, ContractError> {
+    let Context { deps, info, .. } = ctx;
+
+    if !TRADER_GRANTS.has(deps.storage, (&info.sender, &grantee)) {
+        return Err(ContractError::ValidationError {
+            msg: format!("no grant to {}", grantee),
+        });
+    }
+
+    TRADER_GRANTS.remove(deps.storage, (&info.sender, &grantee));
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "revoke"),
+        attr("granter", info.sender.to_string()),
+        attr("grantee", grantee.to_string()),
+    ]))
+}
@@ -1,9 +1,37 @@
+pub mod against;
+pub mod batch;
 pub mod buy;
+pub mod buy_exact_out;
 pub mod claim;
+pub mod complete_set;
+pub mod deposit;
+pub mod grant;
+pub mod pause;
+pub mod register_pubkey;
+pub mod relay;
+pub mod resolve;
+pub mod roles;
 pub mod sell;
+pub mod sell_all;
+pub mod sell_exact_out;
+pub mod set_fees;
 pub mod swap;
+pub mod swap_multi;
+pub mod update_config;
+pub mod withdraw;
 
-use cosmwasm_std::{DepsMut, Env, MessageInfo, Reply};
+use cosmwasm_std::{
+    attr, Addr, Attribute, DepsMut, Env, MessageInfo, Reply, Storage, SubMsg, Uint128,
+};
+
+use cw_utils::must_pay;
+
+use crate::{
+    error::ContractError,
+    math::sub_u128,
+    state::{models::QuoteAccount, storage::TRADE_SEQ},
+    token::Token,
+};
 
 pub struct Context<'a> {
     pub deps: DepsMut<'a>,
@@ -16,3 +44,107 @@ pub struct ReplyContext<'a> {
     pub env: Env,
     pub reply: Reply,
 }
+
+/// Amounts exchanged with a single pool in one leg of a trade. The in_amount
+/// is net of fee_amount.
+pub struct Fill {
+    pub in_amount: Uint128,
+    pub out_amount: Uint128,
+    pub fee_amount: Uint128,
+}
+
+/// Collect amount of quote owed by a trader, either from their internal quote
+/// balance or from funds attached by the sender. Attached funds must be a
+/// single coin of the quote denom; any excess is refunded to the sender via
+/// the returned submsg.
+pub fn collect_quote(
+    store: &mut dyn Storage,
+    info: &MessageInfo,
+    quote_token: &Token,
+    trader: &Addr,
+    amount: Uint128,
+    use_internal_balance: bool,
+) -> Result<Option<SubMsg>, ContractError> {
+    if use_internal_balance {
+        debit_internal_quote(store, info, trader, amount)?;
+        return Ok(None);
+    }
+
+    let denom = quote_token
+        .get_denom()
+        .ok_or_else(|| ContractError::NotImplemented {
+            msg: "quote token cannot be paid with attached funds".to_owned(),
+        })?;
+
+    let funds_amount = must_pay(info, &denom)?;
+    if funds_amount < amount {
+        return Err(ContractError::InsufficientFunds {
+            msg: format!("requires {} quote but received {}", amount, funds_amount),
+        });
+    }
+
+    let refund_amount = sub_u128(funds_amount, amount)?;
+    if refund_amount.is_zero() {
+        Ok(None)
+    } else {
+        Ok(Some(quote_token.transfer(&info.sender, refund_amount)?))
+    }
+}
+
+/// Take quote owed by a trader out of their internal quote balance. Funds may
+/// not also be attached.
+pub fn debit_internal_quote(
+    store: &mut dyn Storage,
+    info: &MessageInfo,
+    trader: &Addr,
+    amount: Uint128,
+) -> Result<(), ContractError> {
+    if !info.funds.is_empty() {
+        return Err(ContractError::ValidationError {
+            msg: "funds cannot be attached when using internal balance".to_owned(),
+        });
+    }
+    QuoteAccount::upsert(store, trader, amount, false)?;
+    Ok(())
+}
+
+/// Pay quote to a recipient by crediting their internal quote balance or by
+/// transfer, returning the transfer submsg if any.
+pub fn pay_quote(
+    store: &mut dyn Storage,
+    quote_token: &Token,
+    recipient: &Addr,
+    amount: Uint128,
+    use_internal_balance: bool,
+) -> Result<Option<SubMsg>, ContractError> {
+    if use_internal_balance {
+        QuoteAccount::upsert(store, recipient, amount, true)?;
+        Ok(None)
+    } else if amount.is_zero() {
+        Ok(None)
+    } else {
+        Ok(Some(quote_token.transfer(recipient, amount)?))
+    }
+}
+
+/// Sequence number of the most recent trade in the global trade feed, or 0 if
+/// there have been none.
+pub fn last_trade_seq(store: &dyn Storage) -> Result<u64, ContractError> {
+    Ok(TRADE_SEQ.may_load(store)?.unwrap_or_default())
+}
+
+/// Attributes spanning the sequence numbers of trades made since prev_seq,
+/// if any.
+pub fn trade_seq_attributes(
+    store: &dyn Storage,
+    prev_seq: u64,
+) -> Result<Vec<Attribute>, ContractError> {
+    let last_seq = last_trade_seq(store)?;
+    if last_seq == prev_seq {
+        return Ok(vec![]);
+    }
+    Ok(vec![
+        attr("first_seq", (prev_seq + 1).to_string()),
+        attr("last_seq", last_seq.to_string()),
+    ])
+}
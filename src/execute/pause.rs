@@ -0,0 +1,55 @@
+use crate::{
+    error::ContractError,
+    msg::{PauseTarget, Role},
+    state::{
+        storage::{CLAIMS_PAUSED, MARKET_PAUSED, PAUSED_POOLS, POOLS},
+        utils::ensure_role,
+    },
+};
+use cosmwasm_std::{attr, Response};
+
+use super::Context;
+
+pub fn exec_pause(
+    ctx: Context,
+    target: PauseTarget,
+    is_paused: bool,
+) -> Result<Response, ContractError> {
+    let Context { deps, info, .. } = ctx;
+    ensure_role(deps.storage, Role::Pauser, &info.sender)?;
+
+    let target_attr = match target {
+        PauseTarget::Market => {
+            MARKET_PAUSED.save(deps.storage, &is_paused)?;
+            "market".to_owned()
+        },
+        PauseTarget::Claims => {
+            CLAIMS_PAUSED.save(deps.storage, &is_paused)?;
+            "claims".to_owned()
+        },
+        PauseTarget::Pools(pool_ids) => {
+            for pool_id in pool_ids.iter() {
+                if !POOLS.has(deps.storage, *pool_id) {
+                    return Err(ContractError::ValidationError {
+                        msg: format!("pool {} does not exist", pool_id),
+                    });
+                }
+                if is_paused {
+                    PAUSED_POOLS.save(deps.storage, *pool_id, &true)?;
+                } else {
+                    PAUSED_POOLS.remove(deps.storage, *pool_id);
+                }
+            }
+            pool_ids
+                .iter()
+                .map(|id| id.to_string())
+                .collect::<Vec<String>>()
+                .join(",")
+        },
+    };
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", if is_paused { "pause" } else { "unpause" }),
+        attr("target", target_attr),
+    ]))
+}
@@ -0,0 +1,25 @@
+use crate::{error::ContractError, state::storage::TRADER_PUBKEYS};
+use cosmwasm_std::{attr, Binary, Response};
+
+use super::Context;
+
+pub fn exec_register_pubkey(
+    ctx: Context,
+    pubkey: Binary,
+) -> Result<Response, ContractError> {
+    let Context { deps, info, .. } = ctx;
+
+    // Accept compressed (33 byte) or uncompressed (65 byte) secp256k1 keys
+    if pubkey.len() != 33 && pubkey.len() != 65 {
+        return Err(ContractError::ValidationError {
+            msg: "pubkey must be a 33 or 65 byte secp256k1 public key".to_owned(),
+        });
+    }
+
+    TRADER_PUBKEYS.save(deps.storage, &info.sender, &pubkey)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "register_pubkey"),
+        attr("address", info.sender.to_string()),
+    ]))
+}
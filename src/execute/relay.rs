@@ -0,0 +1,113 @@
+use crate::{
+    error::ContractError,
+    math::add_u64,
+    msg::{IntentAction, IntentSignDoc, RelayParams, Role, TradeIntent},
+    state::{
+        storage::{INTENT_NONCES, TRADER_PUBKEYS},
+        utils::ensure_role,
+    },
+};
+use cosmwasm_std::{to_json_vec, Addr, Response};
+use cw_utils::nonpayable;
+use sha2::{Digest, Sha256};
+
+use super::{buy::exec_buy, sell::exec_sell, swap::exec_swap, Context};
+
+/// Execute a buy, sell or swap on behalf of a user who has authorized it by
+/// signing a TradeIntent with their registered secp256k1 key.
+pub fn exec_relay(
+    mut ctx: Context,
+    params: RelayParams,
+) -> Result<Response, ContractError> {
+    let RelayParams { intent, signature } = params;
+    let TradeIntent {
+        signer,
+        nonce,
+        expires,
+        action,
+    } = intent.clone();
+
+    let signer = ctx.deps.api.addr_validate(signer.as_str())?;
+
+    // Only relayers may relay signed intents
+    ensure_role(ctx.deps.storage, Role::Relayer, &ctx.info.sender)?;
+
+    // Funds attached by the relayer are not the signer's to spend, so buys must
+    // draw on the signer's internal balance
+    nonpayable(&ctx.info)?;
+
+    if ctx.env.block.time > expires {
+        return Err(ContractError::Expired {
+            msg: "intent expired".to_owned(),
+        });
+    }
+
+    let pubkey = TRADER_PUBKEYS
+        .may_load(ctx.deps.storage, &signer)?
+        .ok_or_else(|| ContractError::NotAuthorized {
+            msg: format!("no pubkey registered for {}", signer),
+        })?;
+
+    // Verify the signature over the sha256 hash of the sign doc
+    let sign_doc = IntentSignDoc {
+        chain_id: ctx.env.block.chain_id.clone(),
+        contract: ctx.env.contract.address.clone(),
+        intent,
+    };
+    let hash = Sha256::digest(to_json_vec(&sign_doc)?);
+    let is_valid = ctx
+        .deps
+        .api
+        .secp256k1_verify(&hash, &signature, &pubkey)
+        .map_err(|e| ContractError::ValidationError { msg: e.to_string() })?;
+
+    if !is_valid {
+        return Err(ContractError::NotAuthorized {
+            msg: "invalid intent signature".to_owned(),
+        });
+    }
+
+    // Consume the nonce so that the intent cannot be replayed
+    let expected_nonce = INTENT_NONCES
+        .may_load(ctx.deps.storage, &signer)?
+        .unwrap_or_default();
+    if nonce != expected_nonce {
+        return Err(ContractError::ValidationError {
+            msg: format!("invalid nonce: expected {}", expected_nonce),
+        });
+    }
+    INTENT_NONCES.save(ctx.deps.storage, &signer, &add_u64(nonce, 1u64)?.u64())?;
+
+    // Execute the intent as if sent by its signer
+    ctx.info.sender = signer.clone();
+
+    let resp = match action {
+        IntentAction::Buy(mut params) => {
+            params.initiator = Some(ensure_initiator(params.initiator, &signer)?);
+            exec_buy(ctx, params)
+        },
+        IntentAction::Sell(mut params) => {
+            params.initiator = Some(ensure_initiator(params.initiator, &signer)?);
+            exec_sell(ctx, params)
+        },
+        IntentAction::Swap(mut params) => {
+            params.initiator = Some(ensure_initiator(params.initiator, &signer)?);
+            exec_swap(ctx, params)
+        },
+    }?;
+
+    Ok(resp.add_attribute("intent_nonce", nonce.to_string()))
+}
+
+/// An intent's action may only name its own signer as initiator.
+fn ensure_initiator(
+    maybe_initiator: Option<Addr>,
+    signer: &Addr,
+) -> Result<Addr, ContractError> {
+    match maybe_initiator {
+        Some(initiator) if initiator != *signer => Err(ContractError::NotAuthorized {
+            msg: "intent initiator must be its signer".to_owned(),
+        }),
+        _ => Ok(signer.clone()),
+    }
+}
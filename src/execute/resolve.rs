@@ -0,0 +1,45 @@
+use crate::{
+    error::ContractError,
+    msg::Role,
+    state::{
+        storage::{PoolId, POOLS, STOP_TIME, WINNING_POOL_ID},
+        utils::ensure_role,
+    },
+};
+use cosmwasm_std::{attr, Response};
+
+use super::Context;
+
+pub fn exec_resolve(
+    ctx: Context,
+    pool_id: PoolId,
+) -> Result<Response, ContractError> {
+    let Context { deps, info, env } = ctx;
+    ensure_role(deps.storage, Role::Resolver, &info.sender)?;
+
+    // The outcome may only be declared once trading has stopped
+    if env.block.time <= STOP_TIME.load(deps.storage)? {
+        return Err(ContractError::NotAuthorized {
+            msg: "the pool is still actively trading".to_owned(),
+        });
+    }
+
+    if WINNING_POOL_ID.exists(deps.storage) {
+        return Err(ContractError::NotAuthorized {
+            msg: "market already resolved".to_owned(),
+        });
+    }
+
+    if !POOLS.has(deps.storage, pool_id) {
+        return Err(ContractError::ValidationError {
+            msg: format!("pool {} does not exist", pool_id),
+        });
+    }
+
+    WINNING_POOL_ID.save(deps.storage, &pool_id)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "resolve"),
+        attr("pool_id", pool_id.to_string()),
+    ]))
+}
@@ -0,0 +1,65 @@
+use crate::{
+    error::ContractError,
+    msg::Role,
+    state::{
+        storage::ROLE_MEMBERS,
+        utils::{ensure_role, has_role},
+    },
+};
+use cosmwasm_std::{attr, Addr, Order, Response};
+
+use super::Context;
+
+pub fn exec_grant_role(
+    ctx: Context,
+    role: Role,
+    address: Addr,
+) -> Result<Response, ContractError> {
+    let Context { deps, info, .. } = ctx;
+    ensure_role(deps.storage, Role::Admin, &info.sender)?;
+
+    let address = deps.api.addr_validate(address.as_str())?;
+    ROLE_MEMBERS.save(deps.storage, (role.to_key(), &address), &true)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "grant_role"),
+        attr("role", role.to_key()),
+        attr("address", address.to_string()),
+    ]))
+}
+
+pub fn exec_revoke_role(
+    ctx: Context,
+    role: Role,
+    address: Addr,
+) -> Result<Response, ContractError> {
+    let Context { deps, info, .. } = ctx;
+    ensure_role(deps.storage, Role::Admin, &info.sender)?;
+
+    if !has_role(deps.storage, role, &address)? {
+        return Err(ContractError::ValidationError {
+            msg: format!("{} is not a member of {}", address, role.to_key()),
+        });
+    }
+
+    // Never leave the contract without an admin
+    if let Role::Admin = role {
+        let n_admins = ROLE_MEMBERS
+            .prefix(Role::Admin.to_key())
+            .keys(deps.storage, None, None, Order::Ascending)
+            .count();
+        if n_admins <= 1 {
+            return Err(ContractError::ValidationError {
+                msg: "cannot revoke the last admin".to_owned(),
+            });
+        }
+    }
+
+    ROLE_MEMBERS.remove(deps.storage, (role.to_key(), &address));
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "revoke_role"),
+        attr("role", role.to_key()),
+        attr("address", address.to_string()),
+    ]))
+}
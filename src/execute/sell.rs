@@ -1,105 +1,230 @@
 use crate::{
     error::ContractError,
     math::{add_u128, add_u32, mul_pct_u128, sub_u128},
-    msg::{PoolAmount, SellParams},
+    msg::{PoolAmount, SellParams, TradeAction, TradeKind},
     state::{
-        models::OhlcBar,
-        storage::{POOL_ACCOUNTS, POOL_STATS, QUOTE_DECIMALS, SELL_FEE_PCT},
-        utils::resolve_initiator,
+        models::{
+            AgainstPosition, OhlcBar, PositionCost, ProbabilitySnapshot, TradeRecord, TraderInfo,
+        },
+        storage::{PoolId, MARKET_STATS, POOL_ACCOUNTS, POOL_STATS, QUOTE_DECIMALS, SELL_FEE_PCT},
+        utils::{
+            ensure_min_trade_size, ensure_not_expired, ensure_pool_not_paused, resolve_initiator,
+        },
     },
 };
 use crate::{
     math::add_u256,
-    state::{
-        models::Pool,
-        storage::{POOLS, QUOTE_TOKEN},
-    },
+    state::{models::Pool, storage::QUOTE_TOKEN},
 };
-use cosmwasm_std::{attr, Response, Uint128};
+use cosmwasm_std::{attr, Addr, Response, Storage, Timestamp, Uint128};
 
-use super::Context;
+use super::{pay_quote, Context, Fill};
 
 pub fn exec_sell(
     ctx: Context,
     params: SellParams,
 ) -> Result<Response, ContractError> {
     let Context { deps, info, env } = ctx;
-    let SellParams { amounts, initiator } = params;
+    let SellParams {
+        amounts,
+        initiator,
+        deadline,
+        valid_until_height,
+        use_internal_balance,
+    } = params;
+    let use_internal_balance = use_internal_balance.unwrap_or(false);
+
+    ensure_not_expired(&env.block, deadline, valid_until_height)?;
     let quote_token = QUOTE_TOKEN.load(deps.storage)?;
-    let quote_decimals = QUOTE_DECIMALS.load(deps.storage)?;
     let fee_pct = SELL_FEE_PCT.load(deps.storage)?;
-    let seller = resolve_initiator(deps.storage, deps.api, &info.sender, initiator)?;
+    let seller = resolve_initiator(
+        deps.storage,
+        deps.api,
+        env.block.time,
+        &info.sender,
+        initiator,
+        TradeKind::Sell,
+        Uint128::zero(),
+    )?;
 
     let mut total_in_amount = Uint128::zero();
     let mut total_out_amount = Uint128::zero();
+    let mut total_fee_amount = Uint128::zero();
 
     for PoolAmount { pool_id, amount } in amounts.iter() {
         let pool_id = *pool_id;
         let in_amount = *amount;
 
-        POOL_ACCOUNTS.update(
-            deps.storage,
-            (&seller, pool_id),
-            |maybe_account| -> Result<_, ContractError> {
-                // Ensure seller has required min balance in pool
-                if let Some(mut account) = maybe_account {
-                    if account.balance < in_amount {
-                        return Err(ContractError::InsufficientFunds {
-                            msg: format!("insufficient balance in pool {}", pool_id),
-                        });
-                    }
-                    account.balance = sub_u128(account.balance, in_amount)?;
-                    Ok(account)
-                } else {
-                    Err(ContractError::NotAuthorized {
-                        msg: format!("account not found for pool {}", pool_id),
-                    })
-                }
-            },
-        )?;
-
-        let mut pool = Pool::load(deps.storage, pool_id)?;
-        let out_amount = pool.sell(in_amount)?;
+        ensure_min_trade_size(deps.storage, TradeKind::Sell, in_amount)?;
 
-        POOLS.save(deps.storage, pool_id, &pool)?;
-
-        // Update statistics pertaining specifically to this pool
-        POOL_STATS.update(
+        let Fill {
+            out_amount,
+            fee_amount,
+            ..
+        } = sell_pool(
             deps.storage,
+            env.block.time,
+            &seller,
             pool_id,
-            |maybe_stats| -> Result<_, ContractError> {
-                if let Some(mut stats) = maybe_stats {
-                    stats.quote_amount_out = add_u256(stats.quote_amount_out, out_amount)?;
-                    stats.base_amount_in = add_u256(stats.base_amount_in, in_amount)?;
-                    stats.num_sells = add_u32(stats.num_buys, 1)?;
-                    stats.fees_collected =
-                        add_u128(stats.fees_collected, mul_pct_u128(out_amount, fee_pct)?)?;
-                    Ok(stats)
-                } else {
-                    return Err(ContractError::NotAuthorized {
-                        msg: format!("could not load stats for pool {}", pool_id),
-                    });
-                }
-            },
+            in_amount,
+            fee_pct,
         )?;
 
-        // Update or add a historical trading OHLC "candlestick"
-        let price = pool.calc_quote_price(quote_decimals)?;
-        OhlcBar::upsert(deps.storage, pool_id, env.block.time, price, out_amount)?;
-
         total_in_amount = add_u128(total_in_amount, in_amount)?;
         total_out_amount = add_u128(total_out_amount, out_amount)?;
+        total_fee_amount = add_u128(total_fee_amount, fee_amount)?;
     }
 
-    let fee_amount = mul_pct_u128(total_out_amount, fee_pct)?;
-    let total_out_amount_post_fee = sub_u128(total_out_amount, fee_amount)?;
+    let total_out_amount_post_fee = sub_u128(total_out_amount, total_fee_amount)?;
+
+    settle_trader_sell(deps.storage, &seller, total_out_amount_post_fee)?;
 
     Ok(Response::new()
-        .add_submessage(quote_token.transfer(&seller, total_out_amount_post_fee)?)
+        .add_submessages(pay_quote(
+            deps.storage,
+            &quote_token,
+            &seller,
+            total_out_amount_post_fee,
+            use_internal_balance,
+        )?)
         .add_attributes(vec![
             attr("action", "sell"),
-            attr("fee_amount", fee_amount.u128().to_string()),
+            attr("fee_amount", total_fee_amount.u128().to_string()),
             attr("in_amount", total_in_amount.u128().to_string()),
             attr("out_amount", total_out_amount.u128().to_string()),
         ]))
 }
+
+/// Swap amount of the seller's base in a pool for quote. The returned fill's
+/// out_amount is before the fee is deducted.
+pub fn sell_pool(
+    store: &mut dyn Storage,
+    time: Timestamp,
+    seller: &Addr,
+    pool_id: PoolId,
+    amount: Uint128,
+    fee_pct: Uint128,
+) -> Result<Fill, ContractError> {
+    ensure_pool_not_paused(store, pool_id)?;
+
+    let mut pool = Pool::load(store, pool_id)?;
+    let out_amount = pool.sell(amount)?;
+    let fee_amount = mul_pct_u128(out_amount, fee_pct)?;
+
+    let fill = Fill {
+        in_amount: amount,
+        out_amount,
+        fee_amount,
+    };
+
+    settle_sell(store, time, seller, pool_id, &pool, &fill)?;
+
+    Ok(fill)
+}
+
+/// Debit the seller's pool account, persist the pool after a sell and record
+/// the fill in the pool's stats and OHLC bars. The fill's out_amount is the
+/// quote amount out before fee_amount is deducted.
+pub fn settle_sell(
+    store: &mut dyn Storage,
+    time: Timestamp,
+    seller: &Addr,
+    pool_id: PoolId,
+    pool: &Pool,
+    fill: &Fill,
+) -> Result<(), ContractError> {
+    let Fill {
+        in_amount,
+        out_amount,
+        fee_amount,
+    } = *fill;
+
+    let account = POOL_ACCOUNTS.update(
+        store,
+        (seller, pool_id),
+        |maybe_account| -> Result<_, ContractError> {
+            // Ensure seller has required min balance in pool
+            if let Some(mut account) = maybe_account {
+                if account.balance < in_amount {
+                    return Err(ContractError::InsufficientFunds {
+                        msg: format!("insufficient balance in pool {}", pool_id),
+                    });
+                }
+                account.balance = sub_u128(account.balance, in_amount)?;
+                Ok(account)
+            } else {
+                Err(ContractError::NotAuthorized {
+                    msg: format!("account not found for pool {}", pool_id),
+                })
+            }
+        },
+    )?;
+
+    // Legs of positions against other pools are held in the balance sold
+    AgainstPosition::clamp_legs(store, seller, pool_id, account.balance)?;
+
+    PositionCost::record_sell(
+        store,
+        seller,
+        pool_id,
+        in_amount,
+        sub_u128(out_amount, fee_amount)?,
+    )?;
+
+    pool.save(store, pool_id)?;
+
+    // Update statistics pertaining specifically to this pool
+    POOL_STATS.update(store, pool_id, |maybe_stats| -> Result<_, ContractError> {
+        if let Some(mut stats) = maybe_stats {
+            stats.quote_amount_out = add_u256(stats.quote_amount_out, out_amount)?;
+            stats.base_amount_in = add_u256(stats.base_amount_in, in_amount)?;
+            stats.num_sells = add_u32(stats.num_sells, 1)?;
+            stats.fees_collected = add_u128(stats.fees_collected, fee_amount)?;
+            Ok(stats)
+        } else {
+            Err(ContractError::NotAuthorized {
+                msg: format!("could not load stats for pool {}", pool_id),
+            })
+        }
+    })?;
+
+    // Update or add a historical trading OHLC "candlestick"
+    let price = pool.calc_quote_price(QUOTE_DECIMALS.load(store)?)?;
+    OhlcBar::upsert(store, pool_id, time, price, out_amount)?;
+    ProbabilitySnapshot::record(store, time)?;
+
+    TradeRecord {
+        action: TradeAction::Sell,
+        pool_ids: vec![pool_id],
+        prices: vec![price],
+        in_amount,
+        out_amount,
+        fee_amount,
+        t: time.seconds().into(),
+    }
+    .append(store, seller)?;
+
+    Ok(())
+}
+
+/// Record a sell yielding out_amount of quote (net of fees) across one or more
+/// pools in the seller's and the market's stats.
+pub fn settle_trader_sell(
+    store: &mut dyn Storage,
+    seller: &Addr,
+    out_amount: Uint128,
+) -> Result<(), ContractError> {
+    TraderInfo::upsert(store, seller, |stats| {
+        stats.num_sells = add_u32(stats.num_sells, 1)?;
+        stats.quote_amount_out = add_u128(stats.quote_amount_out, out_amount)?;
+        Ok(())
+    })?;
+
+    MARKET_STATS.update(store, |mut stats| -> Result<_, ContractError> {
+        stats.num_sells = add_u32(stats.num_sells, 1)?;
+        stats.quote_amount_out = add_u256(stats.quote_amount_out, out_amount)?;
+        Ok(stats)
+    })?;
+
+    Ok(())
+}
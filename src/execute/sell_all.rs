@@ -0,0 +1,129 @@
+use crate::{
+    error::ContractError,
+    math::{add_u128, sub_u128},
+    msg::{SellAllParams, TradeKind},
+    state::{
+        storage::{PoolId, CONFIG, POOL_ACCOUNTS, QUOTE_TOKEN, SELL_FEE_PCT},
+        utils::{ensure_not_expired, resolve_initiator},
+    },
+};
+use cosmwasm_std::{attr, Order, Response, Uint128};
+
+use super::{
+    pay_quote,
+    sell::{sell_pool, settle_trader_sell},
+    Context, Fill,
+};
+
+pub fn exec_sell_all(
+    ctx: Context,
+    params: SellAllParams,
+) -> Result<Response, ContractError> {
+    let Context { deps, info, env } = ctx;
+    let SellAllParams {
+        initiator,
+        pools,
+        min_total_out,
+        deadline,
+        valid_until_height,
+        use_internal_balance,
+    } = params;
+    let use_internal_balance = use_internal_balance.unwrap_or(false);
+
+    ensure_not_expired(&env.block, deadline, valid_until_height)?;
+
+    let seller = resolve_initiator(
+        deps.storage,
+        deps.api,
+        env.block.time,
+        &info.sender,
+        initiator,
+        TradeKind::Sell,
+        Uint128::zero(),
+    )?;
+
+    // Read the seller's current balances in the selected pools, leaving behind
+    // any below the minimum sell size
+    let min_sell_amount = CONFIG
+        .may_load(deps.storage)?
+        .unwrap_or_default()
+        .min_sell_amount;
+    let balances: Vec<(PoolId, Uint128)> = POOL_ACCOUNTS
+        .prefix(&seller)
+        .range(deps.storage, None, None, Order::Ascending)
+        .filter_map(|result| match result {
+            Ok((pool_id, account)) => {
+                let is_selected = pools
+                    .as_ref()
+                    .map(|pool_ids| pool_ids.contains(&pool_id))
+                    .unwrap_or(true);
+                if is_selected && !account.balance.is_zero() && account.balance >= min_sell_amount {
+                    Some(Ok((pool_id, account.balance)))
+                } else {
+                    None
+                }
+            },
+            Err(e) => Some(Err(e)),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if balances.is_empty() {
+        return Err(ContractError::InsufficientBalance {
+            msg: "nothing to sell".to_owned(),
+        });
+    }
+
+    let fee_pct = SELL_FEE_PCT.load(deps.storage)?;
+    let mut total_in_amount = Uint128::zero();
+    let mut total_out_amount = Uint128::zero();
+    let mut total_fee_amount = Uint128::zero();
+
+    for (pool_id, balance) in balances.iter() {
+        let Fill {
+            out_amount,
+            fee_amount,
+            ..
+        } = sell_pool(
+            deps.storage,
+            env.block.time,
+            &seller,
+            *pool_id,
+            *balance,
+            fee_pct,
+        )?;
+
+        total_in_amount = add_u128(total_in_amount, *balance)?;
+        total_out_amount = add_u128(total_out_amount, out_amount)?;
+        total_fee_amount = add_u128(total_fee_amount, fee_amount)?;
+    }
+
+    let total_out_amount_post_fee = sub_u128(total_out_amount, total_fee_amount)?;
+
+    if total_out_amount_post_fee < min_total_out {
+        return Err(ContractError::ValidationError {
+            msg: format!(
+                "sell yields {}, below min_total_out",
+                total_out_amount_post_fee
+            ),
+        });
+    }
+
+    settle_trader_sell(deps.storage, &seller, total_out_amount_post_fee)?;
+
+    let quote_token = QUOTE_TOKEN.load(deps.storage)?;
+
+    Ok(Response::new()
+        .add_submessages(pay_quote(
+            deps.storage,
+            &quote_token,
+            &seller,
+            total_out_amount_post_fee,
+            use_internal_balance,
+        )?)
+        .add_attributes(vec![
+            attr("action", "sell_all"),
+            attr("fee_amount", total_fee_amount.u128().to_string()),
+            attr("in_amount", total_in_amount.u128().to_string()),
+            attr("out_amount", total_out_amount.u128().to_string()),
+        ]))
+}
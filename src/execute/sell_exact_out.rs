@@ -0,0 +1,105 @@
+use crate::{
+    error::ContractError,
+    math::{mul_ratio_ceil_u128, sub_u128},
+    msg::{SellExactOutParams, TradeKind},
+    state::{
+        models::Pool,
+        storage::{QUOTE_TOKEN, SELL_FEE_PCT},
+        utils::{
+            ensure_min_trade_size, ensure_not_expired, ensure_pool_not_paused, resolve_initiator,
+        },
+    },
+};
+use cosmwasm_std::{attr, Response, Uint128};
+
+use super::{
+    pay_quote,
+    sell::{settle_sell, settle_trader_sell},
+    Context, Fill,
+};
+
+pub fn exec_sell_exact_out(
+    ctx: Context,
+    params: SellExactOutParams,
+) -> Result<Response, ContractError> {
+    let Context { deps, info, env } = ctx;
+    let SellExactOutParams {
+        initiator,
+        pool_id,
+        quote_out,
+        max_base_in,
+        deadline,
+        valid_until_height,
+        use_internal_balance,
+    } = params;
+    let use_internal_balance = use_internal_balance.unwrap_or(false);
+
+    ensure_not_expired(&env.block, deadline, valid_until_height)?;
+    let quote_token = QUOTE_TOKEN.load(deps.storage)?;
+    let fee_pct = SELL_FEE_PCT.load(deps.storage)?;
+    let seller = resolve_initiator(
+        deps.storage,
+        deps.api,
+        env.block.time,
+        &info.sender,
+        initiator,
+        TradeKind::Sell,
+        Uint128::zero(),
+    )?;
+
+    if quote_out.is_zero() {
+        return Err(ContractError::ValidationError {
+            msg: "cannot sell for 0 amount".to_owned(),
+        });
+    }
+
+    ensure_pool_not_paused(deps.storage, pool_id)?;
+
+    // Gross up the requested quote so that it remains after deducting the fee,
+    // then compute the base required by the curve to release it.
+    let out_amount =
+        mul_ratio_ceil_u128(quote_out, 1_000_000u128, sub_u128(1_000_000u128, fee_pct)?)?;
+    let fee_amount = sub_u128(out_amount, quote_out)?;
+
+    let mut pool = Pool::load(deps.storage, pool_id)?;
+    let in_amount = pool.sell_exact_out(out_amount)?;
+
+    ensure_min_trade_size(deps.storage, TradeKind::Sell, in_amount)?;
+
+    if in_amount > max_base_in {
+        return Err(ContractError::ValidationError {
+            msg: format!("sell requires {} base, exceeding max_base_in", in_amount),
+        });
+    }
+
+    settle_sell(
+        deps.storage,
+        env.block.time,
+        &seller,
+        pool_id,
+        &pool,
+        &Fill {
+            in_amount,
+            out_amount,
+            fee_amount,
+        },
+    )?;
+
+    settle_trader_sell(deps.storage, &seller, quote_out)?;
+
+    Ok(Response::new()
+        .add_submessages(pay_quote(
+            deps.storage,
+            &quote_token,
+            &seller,
+            quote_out,
+            use_internal_balance,
+        )?)
+        .add_attributes(vec![
+            attr("action", "sell_exact_out"),
+            attr("pool_id", pool_id.to_string()),
+            attr("fee_amount", fee_amount.u128().to_string()),
+            attr("in_amount", in_amount.u128().to_string()),
+            attr("out_amount", out_amount.u128().to_string()),
+        ]))
+}
@@ -0,0 +1,56 @@
+use crate::{
+    error::ContractError,
+    msg::{FeeInitArgs, Role},
+    state::{
+        storage::{BUY_FEE_PCT, CLAIM_FEE_PCT, FEE_MANAGER_ADDR, SELL_FEE_PCT, SWAP_FEE_PCT},
+        utils::ensure_role,
+    },
+};
+use cosmwasm_std::{attr, Response, Uint128};
+
+use super::Context;
+
+pub fn exec_set_fees(
+    ctx: Context,
+    fees: FeeInitArgs,
+) -> Result<Response, ContractError> {
+    let Context { deps, info, .. } = ctx;
+    let FeeInitArgs {
+        manager,
+        pct_swap,
+        pct_buy,
+        pct_sell,
+        pct_claim,
+    } = fees;
+
+    ensure_role(deps.storage, Role::FeeManager, &info.sender)?;
+
+    // Fee percentages are expressed in millionths
+    for pct in [pct_swap, pct_buy, pct_sell, pct_claim.unwrap_or_default()] {
+        if pct > Uint128::from(1_000_000u128) {
+            return Err(ContractError::ValidationError {
+                msg: "fee pct cannot exceed 100%".to_owned(),
+            });
+        }
+    }
+
+    BUY_FEE_PCT.save(deps.storage, &pct_buy)?;
+    SELL_FEE_PCT.save(deps.storage, &pct_sell)?;
+    SWAP_FEE_PCT.save(deps.storage, &pct_swap)?;
+
+    if let Some(pct_claim) = pct_claim {
+        CLAIM_FEE_PCT.save(deps.storage, &pct_claim)?;
+    }
+
+    if let Some(manager) = manager {
+        FEE_MANAGER_ADDR.save(deps.storage, &deps.api.addr_validate(manager.as_str())?)?;
+    }
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "set_fees"),
+        attr("pct_buy", pct_buy.to_string()),
+        attr("pct_sell", pct_sell.to_string()),
+        attr("pct_swap", pct_swap.to_string()),
+        attr("pct_claim", CLAIM_FEE_PCT.load(deps.storage)?.to_string()),
+    ]))
+}
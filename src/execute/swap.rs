@@ -3,22 +3,33 @@ use crate::{
     math::{add_u128, add_u256, add_u32, mul_pct_u128, sub_u128},
     msg::SwapStats,
     state::{
-        models::OhlcBar,
-        storage::{POOL_STATS, QUOTE_DECIMALS, SWAP_STATS},
-        utils::resolve_initiator,
+        models::{OhlcBar, PositionCost, ProbabilitySnapshot, TradeRecord, TraderInfo},
+        storage::{PoolId, MARKET_STATS, POOL_STATS, QUOTE_DECIMALS, SWAP_STATS},
+        utils::{
+            ensure_min_trade_size, ensure_not_expired, ensure_pool_not_paused, resolve_initiator,
+        },
     },
 };
 use crate::{
-    msg::SwapParams,
+    msg::{SwapParams, TradeAction, TradeKind},
     state::{
         models::{Pool, PoolAccount},
-        storage::{FEE_MANAGER_ADDR, POOLS, QUOTE_TOKEN, SWAP_FEE_PCT},
+        storage::{FEE_MANAGER_ADDR, QUOTE_TOKEN, SWAP_FEE_PCT},
     },
 };
-use cosmwasm_std::{attr, Response, Uint256};
+use cosmwasm_std::{attr, Addr, Response, Storage, Timestamp, Uint128, Uint256};
 
 use super::Context;
 
+/// Result of swapping base from one pool into another.
+pub struct SwapOutcome {
+    pub quote_amount: Uint128,
+    pub fee_amount: Uint128,
+    pub to_amount: Uint128,
+    pub from_pool: Pool,
+    pub to_pool: Pool,
+}
+
 pub fn exec_swap(
     ctx: Context,
     params: SwapParams,
@@ -29,38 +40,221 @@ pub fn exec_swap(
         from_pool: from_pool_id,
         to_pool: to_pool_id,
         initiator,
+        deadline,
+        valid_until_height,
     } = params;
 
-    let initiator = resolve_initiator(deps.storage, deps.api, &info.sender, initiator)?;
+    ensure_not_expired(&env.block, deadline, valid_until_height)?;
 
-    let mut from_pool = Pool::load(deps.storage, from_pool_id)?;
-    let mut to_pool = Pool::load(deps.storage, to_pool_id)?;
+    let initiator = resolve_initiator(
+        deps.storage,
+        deps.api,
+        env.block.time,
+        &info.sender,
+        initiator,
+        TradeKind::Swap,
+        Uint128::zero(),
+    )?;
 
     let quote_token = QUOTE_TOKEN.load(deps.storage)?;
-    let quote_decimals = QUOTE_DECIMALS.load(deps.storage)?;
-    let quote_amount = from_pool.swap(from_amount, false)?;
 
-    let fee_amount = mul_pct_u128(quote_amount, SWAP_FEE_PCT.load(deps.storage)?)?;
-    let to_amount = to_pool.swap(sub_u128(quote_amount, fee_amount)?, true)?;
+    let SwapOutcome {
+        quote_amount,
+        fee_amount,
+        to_amount,
+        from_pool,
+        to_pool,
+    } = swap_pools(
+        deps.storage,
+        env.block.time,
+        &initiator,
+        from_pool_id,
+        to_pool_id,
+        from_amount,
+    )?;
 
-    PoolAccount::upsert(deps.storage, &initiator, from_pool_id, from_amount, false)?;
-    PoolAccount::upsert(deps.storage, &initiator, to_pool_id, to_amount, true)?;
+    let mut resp = Response::new();
 
-    POOLS.save(deps.storage, from_pool_id, &from_pool)?;
-    POOLS.save(deps.storage, to_pool_id, &to_pool)?;
+    if !fee_amount.is_zero() {
+        resp = resp.add_submessage(
+            quote_token.transfer(&FEE_MANAGER_ADDR.load(deps.storage)?, fee_amount)?,
+        );
+    }
 
-    POOL_STATS.update(
-        deps.storage,
-        from_pool_id,
-        |stats| -> Result<_, ContractError> {
-            let mut stats = stats.unwrap();
-            stats.fees_collected = add_u128(stats.fees_collected, fee_amount)?;
-            Ok(stats)
-        },
+    Ok(resp.add_attributes(vec![
+        attr("action", "swap"),
+        attr("quote_amount", quote_amount.to_string()),
+        attr("fee_amount", fee_amount.to_string()),
+        attr("from_pool_id", from_pool_id.to_string()),
+        attr("from_amount", from_amount.to_string()),
+        attr("to_pool_id", to_pool_id.to_string()),
+        attr("to_amount", to_amount.to_string()),
+        attr("from_pool_reserve", from_pool.reserves.base.to_string()),
+        attr("to_pool_reserve", to_pool.reserves.base.to_string()),
+    ]))
+}
+
+/// Sell from_amount of the trader's base in from_pool for quote and, after
+/// deducting the swap fee, buy into to_pool with it. Persists both pools and
+/// records the swap in the trader's pool accounts, stats and OHLC bars. The
+/// caller is responsible for transferring the returned fee_amount.
+pub fn swap_pools(
+    store: &mut dyn Storage,
+    time: Timestamp,
+    trader: &Addr,
+    from_pool_id: PoolId,
+    to_pool_id: PoolId,
+    from_amount: Uint128,
+) -> Result<SwapOutcome, ContractError> {
+    if from_pool_id == to_pool_id {
+        return Err(ContractError::ValidationError {
+            msg: "cannot swap a pool with itself".to_owned(),
+        });
+    }
+
+    ensure_min_trade_size(store, TradeKind::Swap, from_amount)?;
+
+    let (quote_amount, from_pool) =
+        swap_out_of_pool(store, time, trader, from_pool_id, from_amount)?;
+
+    let fee_amount = mul_pct_u128(quote_amount, SWAP_FEE_PCT.load(store)?)?;
+    record_swap_fee(store, from_pool_id, fee_amount)?;
+
+    let (to_amount, to_pool) = swap_into_pool(
+        store,
+        time,
+        trader,
+        to_pool_id,
+        sub_u128(quote_amount, fee_amount)?,
     )?;
 
+    // The swap fee is borne by the position swapped out of
+    let net_quote_amount = sub_u128(quote_amount, fee_amount)?;
+    PositionCost::record_sell(store, trader, from_pool_id, from_amount, net_quote_amount)?;
+    PositionCost::record_buy(store, trader, to_pool_id, net_quote_amount)?;
+
+    record_swap_stats(store, from_pool_id, to_pool_id, from_amount, to_amount)?;
+    settle_trader_swap(store, trader)?;
+
+    let quote_decimals = QUOTE_DECIMALS.load(store)?;
+    TradeRecord {
+        action: TradeAction::Swap,
+        pool_ids: vec![from_pool_id, to_pool_id],
+        prices: vec![
+            from_pool.calc_quote_price(quote_decimals)?,
+            to_pool.calc_quote_price(quote_decimals)?,
+        ],
+        in_amount: from_amount,
+        out_amount: to_amount,
+        fee_amount,
+        t: time.seconds().into(),
+    }
+    .append(store, trader)?;
+
+    Ok(SwapOutcome {
+        quote_amount,
+        fee_amount,
+        to_amount,
+        from_pool,
+        to_pool,
+    })
+}
+
+/// Sell the trader's base in a pool for quote as the first half of a swap,
+/// returning the quote amount (before swap fees) and the updated pool.
+pub fn swap_out_of_pool(
+    store: &mut dyn Storage,
+    time: Timestamp,
+    trader: &Addr,
+    pool_id: PoolId,
+    amount: Uint128,
+) -> Result<(Uint128, Pool), ContractError> {
+    ensure_pool_not_paused(store, pool_id)?;
+
+    let mut pool = Pool::load(store, pool_id)?;
+    let quote_amount = pool.swap(amount, false)?;
+
+    PoolAccount::upsert(store, trader, pool_id, amount, false)?;
+    pool.save(store, pool_id)?;
+
+    POOL_STATS.update(store, pool_id, |maybe_stats| -> Result<_, ContractError> {
+        let mut stats = maybe_stats.ok_or_else(|| ContractError::NotAuthorized {
+            msg: format!("could not load stats for pool {}", pool_id),
+        })?;
+        stats.base_amount_in = add_u256(stats.base_amount_in, amount)?;
+        stats.quote_amount_out = add_u256(stats.quote_amount_out, quote_amount)?;
+        stats.num_swaps = add_u32(stats.num_swaps, 1)?;
+        Ok(stats)
+    })?;
+
+    // Update or add a historical trading OHLC "candlestick"
+    let price = pool.calc_quote_price(QUOTE_DECIMALS.load(store)?)?;
+    OhlcBar::upsert(store, pool_id, time, price, quote_amount)?;
+
+    Ok((quote_amount, pool))
+}
+
+/// Buy into a pool with quote (net of swap fees) as the second half of a
+/// swap, returning the base amount credited to the trader and the updated pool.
+pub fn swap_into_pool(
+    store: &mut dyn Storage,
+    time: Timestamp,
+    trader: &Addr,
+    pool_id: PoolId,
+    quote_amount: Uint128,
+) -> Result<(Uint128, Pool), ContractError> {
+    ensure_pool_not_paused(store, pool_id)?;
+
+    let mut pool = Pool::load(store, pool_id)?;
+    let amount = pool.swap(quote_amount, true)?;
+
+    PoolAccount::upsert(store, trader, pool_id, amount, true)?;
+    pool.save(store, pool_id)?;
+
+    POOL_STATS.update(store, pool_id, |maybe_stats| -> Result<_, ContractError> {
+        let mut stats = maybe_stats.ok_or_else(|| ContractError::NotAuthorized {
+            msg: format!("could not load stats for pool {}", pool_id),
+        })?;
+        stats.quote_amount_in = add_u256(stats.quote_amount_in, quote_amount)?;
+        stats.base_amount_out = add_u256(stats.base_amount_out, amount)?;
+        stats.num_swaps = add_u32(stats.num_swaps, 1)?;
+        Ok(stats)
+    })?;
+
+    // Update or add a historical trading OHLC "candlestick"
+    let price = pool.calc_quote_price(QUOTE_DECIMALS.load(store)?)?;
+    OhlcBar::upsert(store, pool_id, time, price, quote_amount)?;
+    ProbabilitySnapshot::record(store, time)?;
+
+    Ok((amount, pool))
+}
+
+/// Attribute a swap fee to the pool swapped out of.
+pub fn record_swap_fee(
+    store: &mut dyn Storage,
+    pool_id: PoolId,
+    fee_amount: Uint128,
+) -> Result<(), ContractError> {
+    POOL_STATS.update(store, pool_id, |maybe_stats| -> Result<_, ContractError> {
+        let mut stats = maybe_stats.ok_or_else(|| ContractError::NotAuthorized {
+            msg: format!("could not load stats for pool {}", pool_id),
+        })?;
+        stats.fees_collected = add_u128(stats.fees_collected, fee_amount)?;
+        Ok(stats)
+    })?;
+    Ok(())
+}
+
+/// Accumulate base in and out of a swap between a pair of pools.
+pub fn record_swap_stats(
+    store: &mut dyn Storage,
+    from_pool_id: PoolId,
+    to_pool_id: PoolId,
+    in_amount: Uint128,
+    out_amount: Uint128,
+) -> Result<(), ContractError> {
     SWAP_STATS.update(
-        deps.storage,
+        store,
         (from_pool_id, to_pool_id),
         |maybe_stats| -> Result<_, ContractError> {
             let mut stats = maybe_stats.unwrap_or_else(|| SwapStats {
@@ -68,40 +262,30 @@ pub fn exec_swap(
                 out_amount: Uint256::zero(),
                 n: 0,
             });
-            stats.in_amount = add_u256(stats.in_amount, from_amount)?;
-            stats.out_amount = add_u256(stats.out_amount, to_amount)?;
+            stats.in_amount = add_u256(stats.in_amount, in_amount)?;
+            stats.out_amount = add_u256(stats.out_amount, out_amount)?;
             stats.n = add_u32(stats.n, 1)?;
             Ok(stats)
         },
     )?;
-    // Update or add a historical trading OHLC "candlestick"
-    OhlcBar::upsert(
-        deps.storage,
-        from_pool_id,
-        env.block.time,
-        from_pool.calc_quote_price(quote_decimals)?,
-        from_amount,
-    )?;
+    Ok(())
+}
 
-    OhlcBar::upsert(
-        deps.storage,
-        to_pool_id,
-        env.block.time,
-        to_pool.calc_quote_price(quote_decimals)?,
-        to_amount,
-    )?;
+/// Record a swap, which may span several pools, in the trader's and the
+/// market's stats.
+pub fn settle_trader_swap(
+    store: &mut dyn Storage,
+    trader: &Addr,
+) -> Result<(), ContractError> {
+    TraderInfo::upsert(store, trader, |stats| {
+        stats.num_swaps = add_u32(stats.num_swaps, 1)?;
+        Ok(())
+    })?;
+
+    MARKET_STATS.update(store, |mut stats| -> Result<_, ContractError> {
+        stats.num_swaps = add_u32(stats.num_swaps, 1)?;
+        Ok(stats)
+    })?;
 
-    Ok(Response::new()
-        .add_submessage(quote_token.transfer(&FEE_MANAGER_ADDR.load(deps.storage)?, fee_amount)?)
-        .add_attributes(vec![
-            attr("action", "swap"),
-            attr("quote_amount", quote_amount.to_string()),
-            attr("fee_amount", fee_amount.to_string()),
-            attr("from_pool_id", from_pool_id.to_string()),
-            attr("from_amount", from_amount.to_string()),
-            attr("to_pool_id", to_pool_id.to_string()),
-            attr("to_amount", to_amount.to_string()),
-            attr("from_pool_reserve", from_pool.reserves.base.to_string()),
-            attr("to_pool_reserve", to_pool.reserves.base.to_string()),
-        ]))
+    Ok(())
 }
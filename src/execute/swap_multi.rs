@@ -0,0 +1,201 @@
+use crate::{
+    error::ContractError,
+    math::{add_u128, mul_pct_u128, mul_ratio_u128, sub_u128},
+    msg::{PoolAmount, PoolWeight, SwapMultiParams, TradeAction, TradeKind},
+    state::{
+        models::{PositionCost, TradeRecord},
+        storage::{FEE_MANAGER_ADDR, QUOTE_DECIMALS, QUOTE_TOKEN, SWAP_FEE_PCT},
+        utils::{ensure_min_trade_size, ensure_not_expired, resolve_initiator},
+    },
+};
+use cosmwasm_std::{attr, Response, Uint128};
+
+use super::{
+    swap::{
+        record_swap_fee, record_swap_stats, settle_trader_swap, swap_into_pool, swap_out_of_pool,
+    },
+    Context,
+};
+
+pub fn exec_swap_multi(
+    ctx: Context,
+    params: SwapMultiParams,
+) -> Result<Response, ContractError> {
+    let Context { deps, info, env } = ctx;
+    let SwapMultiParams {
+        initiator,
+        from,
+        to,
+        deadline,
+        valid_until_height,
+    } = params;
+
+    ensure_not_expired(&env.block, deadline, valid_until_height)?;
+
+    if from.is_empty() || to.is_empty() {
+        return Err(ContractError::ValidationError {
+            msg: "swap requires at least one source and one target pool".to_owned(),
+        });
+    }
+
+    for PoolAmount { pool_id, .. } in from.iter() {
+        let n_sources = from.iter().filter(|x| x.pool_id == *pool_id).count();
+        if n_sources > 1 || to.iter().any(|x| x.pool_id == *pool_id) {
+            return Err(ContractError::ValidationError {
+                msg: format!("pool {} appears more than once", pool_id),
+            });
+        }
+    }
+
+    for PoolWeight { pool_id, weight } in to.iter() {
+        if *weight == 0 || to.iter().filter(|x| x.pool_id == *pool_id).count() > 1 {
+            return Err(ContractError::ValidationError {
+                msg: format!("invalid target pool {}", pool_id),
+            });
+        }
+    }
+
+    let initiator = resolve_initiator(
+        deps.storage,
+        deps.api,
+        env.block.time,
+        &info.sender,
+        initiator,
+        TradeKind::Swap,
+        Uint128::zero(),
+    )?;
+
+    let quote_token = QUOTE_TOKEN.load(deps.storage)?;
+    let fee_pct = SWAP_FEE_PCT.load(deps.storage)?;
+
+    // Swap out of each source pool into an intermediate quote amount
+    let quote_decimals = QUOTE_DECIMALS.load(deps.storage)?;
+    let mut quote_amounts: Vec<Uint128> = Vec::with_capacity(from.len());
+    let mut total_quote_amount = Uint128::zero();
+    let mut total_from_amount = Uint128::zero();
+    let mut prices: Vec<Uint128> = Vec::with_capacity(from.len() + to.len());
+    for PoolAmount { pool_id, amount } in from.iter() {
+        ensure_min_trade_size(deps.storage, TradeKind::Swap, *amount)?;
+        let (quote_amount, pool) =
+            swap_out_of_pool(deps.storage, env.block.time, &initiator, *pool_id, *amount)?;
+        total_quote_amount = add_u128(total_quote_amount, quote_amount)?;
+        total_from_amount = add_u128(total_from_amount, *amount)?;
+        quote_amounts.push(quote_amount);
+        prices.push(pool.calc_quote_price(quote_decimals)?);
+    }
+
+    // Charge the swap fee once on the intermediate quote amount, attributing
+    // it to source pools in proportion to the quote each contributed.
+    let fee_amount = mul_pct_u128(total_quote_amount, fee_pct)?;
+    let mut unattributed_fee_amount = fee_amount;
+    for (i, PoolAmount { pool_id, amount }) in from.iter().enumerate() {
+        let pool_fee_amount = if i + 1 == from.len() {
+            unattributed_fee_amount
+        } else {
+            mul_ratio_u128(fee_amount, quote_amounts[i], total_quote_amount)?
+        };
+        unattributed_fee_amount = sub_u128(unattributed_fee_amount, pool_fee_amount)?;
+        record_swap_fee(deps.storage, *pool_id, pool_fee_amount)?;
+        PositionCost::record_sell(
+            deps.storage,
+            &initiator,
+            *pool_id,
+            *amount,
+            sub_u128(quote_amounts[i], pool_fee_amount)?,
+        )?;
+    }
+
+    // Spread the remaining quote over target pools by weight
+    let net_quote_amount = sub_u128(total_quote_amount, fee_amount)?;
+    let total_weight: u128 = to.iter().map(|x| x.weight as u128).sum();
+    let mut unallocated_quote_amount = net_quote_amount;
+    let mut to_amounts: Vec<Uint128> = Vec::with_capacity(to.len());
+    for (j, PoolWeight { pool_id, weight }) in to.iter().enumerate() {
+        let quote_amount = if j + 1 == to.len() {
+            unallocated_quote_amount
+        } else {
+            mul_ratio_u128(net_quote_amount, *weight as u128, total_weight)?
+        };
+        unallocated_quote_amount = sub_u128(unallocated_quote_amount, quote_amount)?;
+        let (to_amount, pool) = swap_into_pool(
+            deps.storage,
+            env.block.time,
+            &initiator,
+            *pool_id,
+            quote_amount,
+        )?;
+        PositionCost::record_buy(deps.storage, &initiator, *pool_id, quote_amount)?;
+        to_amounts.push(to_amount);
+        prices.push(pool.calc_quote_price(quote_decimals)?);
+    }
+
+    // Apportion flows to each (source, target) pair: the source's base in is
+    // split by target weight and the target's base out by source quote share.
+    for (i, PoolAmount { pool_id, amount }) in from.iter().enumerate() {
+        for (
+            j,
+            PoolWeight {
+                pool_id: to_pool_id,
+                weight,
+            },
+        ) in to.iter().enumerate()
+        {
+            record_swap_stats(
+                deps.storage,
+                *pool_id,
+                *to_pool_id,
+                mul_ratio_u128(*amount, *weight as u128, total_weight)?,
+                mul_ratio_u128(to_amounts[j], quote_amounts[i], total_quote_amount)?,
+            )?;
+        }
+    }
+
+    settle_trader_swap(deps.storage, &initiator)?;
+
+    // Record the swap as one trade, with base amounts totaled across pools
+    TradeRecord {
+        action: TradeAction::Swap,
+        pool_ids: from
+            .iter()
+            .map(|x| x.pool_id)
+            .chain(to.iter().map(|x| x.pool_id))
+            .collect(),
+        prices,
+        in_amount: total_from_amount,
+        out_amount: to_amounts
+            .iter()
+            .try_fold(Uint128::zero(), |total, x| add_u128(total, *x))?,
+        fee_amount,
+        t: env.block.time.seconds().into(),
+    }
+    .append(deps.storage, &initiator)?;
+
+    let mut resp = Response::new();
+
+    if !fee_amount.is_zero() {
+        resp = resp.add_submessage(
+            quote_token.transfer(&FEE_MANAGER_ADDR.load(deps.storage)?, fee_amount)?,
+        );
+    }
+
+    Ok(resp.add_attributes(vec![
+        attr("action", "swap_multi"),
+        attr("quote_amount", total_quote_amount.to_string()),
+        attr("fee_amount", fee_amount.to_string()),
+        attr(
+            "from",
+            from.iter()
+                .map(|x| format!("{}:{}", x.pool_id, x.amount))
+                .collect::<Vec<String>>()
+                .join(","),
+        ),
+        attr(
+            "to",
+            to.iter()
+                .zip(to_amounts.iter())
+                .map(|(x, amount)| format!("{}:{}", x.pool_id, amount))
+                .collect::<Vec<String>>()
+                .join(","),
+        ),
+    ]))
+}
@@ -0,0 +1,26 @@
+use crate::{
+    error::ContractError,
+    msg::Role,
+    state::{models::Config, storage::CONFIG, utils::ensure_role},
+};
+use cosmwasm_std::{attr, Response};
+
+use super::Context;
+
+pub fn exec_update_config(
+    ctx: Context,
+    config: Config,
+) -> Result<Response, ContractError> {
+    let Context { deps, info, .. } = ctx;
+
+    ensure_role(deps.storage, Role::Admin, &info.sender)?;
+
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "update_config"),
+        attr("min_buy_amount", config.min_buy_amount.to_string()),
+        attr("min_sell_amount", config.min_sell_amount.to_string()),
+        attr("min_swap_amount", config.min_swap_amount.to_string()),
+    ]))
+}
@@ -0,0 +1,37 @@
+use crate::{
+    error::ContractError,
+    state::{models::QuoteAccount, storage::QUOTE_TOKEN, utils::ensure_solvent},
+};
+use cosmwasm_std::{attr, Response, Uint128};
+
+use super::Context;
+
+/// Transfer amount out of the sender's internal quote balance.
+pub fn exec_withdraw(
+    ctx: Context,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    let Context { deps, info, env } = ctx;
+
+    if amount.is_zero() {
+        return Err(ContractError::ValidationError {
+            msg: "cannot withdraw 0".to_owned(),
+        });
+    }
+
+    let account = QuoteAccount::upsert(deps.storage, &info.sender, amount, false)?;
+
+    ensure_solvent(deps.storage, deps.querier, &env.contract.address, amount)?;
+
+    Ok(Response::new()
+        .add_submessage(
+            QUOTE_TOKEN
+                .load(deps.storage)?
+                .transfer(&info.sender, amount)?,
+        )
+        .add_attributes(vec![
+            attr("action", "withdraw"),
+            attr("amount", amount.u128().to_string()),
+            attr("balance", account.balance.u128().to_string()),
+        ]))
+}
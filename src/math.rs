@@ -1,4 +1,4 @@
-use cosmwasm_std::{OverflowError, OverflowOperation, StdError, Uint128, Uint256, Uint64};
+use cosmwasm_std::{Int256, OverflowError, OverflowOperation, StdError, Uint128, Uint256, Uint64};
 
 use crate::error::ContractError;
 
@@ -42,6 +42,45 @@ pub fn div_u256<A: Into<Uint256>, B: Into<Uint256>>(
         .map_err(|e| ContractError::Std(StdError::divide_by_zero(e)))
 }
 
+/// Divide, rounding up instead of truncating.
+pub fn div_ceil_u256<A: Into<Uint256>, B: Into<Uint256>>(
+    numerator: A,
+    denominator: B,
+) -> Result<Uint256, ContractError> {
+    let a: Uint256 = numerator.into();
+    let b: Uint256 = denominator.into();
+    let quotient = div_u256(a, b)?;
+    if quotient
+        .checked_mul(b)
+        .map_err(|e| ContractError::Std(StdError::overflow(e)))?
+        == a
+    {
+        Ok(quotient)
+    } else {
+        add_u256(quotient, Uint256::one())
+    }
+}
+
+pub fn add_i256<A: Into<Int256>, B: Into<Int256>>(
+    a: A,
+    b: B,
+) -> Result<Int256, ContractError> {
+    let a: Int256 = a.into();
+    let b: Int256 = b.into();
+    a.checked_add(b)
+        .map_err(|e| ContractError::Std(StdError::overflow(e)))
+}
+
+pub fn sub_i256<A: Into<Int256>, B: Into<Int256>>(
+    a: A,
+    b: B,
+) -> Result<Int256, ContractError> {
+    let a: Int256 = a.into();
+    let b: Int256 = b.into();
+    a.checked_sub(b)
+        .map_err(|e| ContractError::Std(StdError::overflow(e)))
+}
+
 pub fn add_u128<A: Into<Uint128>, B: Into<Uint128>>(
     a: A,
     b: B,
@@ -94,6 +133,20 @@ pub fn mul_ratio_u128<A: Into<Uint128>, B: Into<Uint128>, C: Into<Uint128>>(
         .map_err(|e| ContractError::Std(StdError::generic_err(e.to_string())))
 }
 
+/// Like mul_ratio_u128 but rounds up instead of truncating.
+pub fn mul_ratio_ceil_u128<A: Into<Uint128>, B: Into<Uint128>, C: Into<Uint128>>(
+    base: A,
+    numerator: B,
+    denominator: C,
+) -> Result<Uint128, ContractError> {
+    let a: Uint128 = base.into();
+    let b: Uint128 = numerator.into();
+    let c: Uint128 = denominator.into();
+    div_ceil_u256(mul_u256(a, b)?, c)?
+        .try_into()
+        .map_err(|e| ContractError::Std(StdError::from(e)))
+}
+
 pub fn mul_pct_u128<A: Into<Uint128>, B: Into<Uint128>>(
     base: A,
     numerator: B,
@@ -117,9 +170,21 @@ pub fn add_u32(
     a: u32,
     b: u32,
 ) -> Result<u32, ContractError> {
-    a.checked_add(1).ok_or_else(|| {
+    a.checked_add(b).ok_or_else(|| {
         ContractError::Std(StdError::Overflow {
             source: OverflowError::new(OverflowOperation::Add, a, b),
         })
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_u32_adds_both_operands() {
+        assert_eq!(add_u32(2, 3).unwrap(), 5);
+        assert_eq!(add_u32(0, 7).unwrap(), 7);
+        assert!(add_u32(u32::MAX, 1).is_err());
+    }
+}
@@ -1,11 +1,12 @@
-use std::ops::Add;
-
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Addr, Timestamp, Uint128, Uint256};
+use cosmwasm_std::{Addr, Binary, Decimal256, Int256, Timestamp, Uint128, Uint256};
 
 use crate::{
     state::{
-        models::{Config, MarketStats, PoolReserves, TraderStats},
+        models::{
+            AgainstPosition, Config, MarketStats, OhlcBar, PoolReserves, ProbabilitySnapshot,
+            SequencedTrade, TradeRecord, TraderGrant, TraderStats,
+        },
         storage::PoolId,
     },
     token::Token,
@@ -26,6 +27,7 @@ pub struct FeeInitArgs {
     pub pct_swap: Uint128,
     pub pct_buy: Uint128,
     pub pct_sell: Uint128,
+    pub pct_claim: Option<Uint128>,
 }
 
 #[cw_serde]
@@ -38,6 +40,7 @@ pub struct InstantiateMsg {
     pub quote_symbol: String,
     pub pools: Vec<PoolInitArgs>,
     pub fees: FeeInitArgs,
+    pub config: Option<Config>,
 }
 
 #[cw_serde]
@@ -45,18 +48,129 @@ pub enum ExecuteMsg {
     Buy(BuyParams),
     Sell(SellParams),
     Swap(SwapParams),
+    BuyExactOut(BuyExactOutParams),
+    SellExactOut(SellExactOutParams),
+    Batch(BatchParams),
+    SwapMulti(SwapMultiParams),
+    MintSet(CompleteSetParams),
+    RedeemSet(CompleteSetParams),
+    BuyAgainst(AgainstParams),
+    SellAgainst(AgainstParams),
+    SellAll(SellAllParams),
+    Deposit {},
+    Withdraw {
+        amount: Uint128,
+    },
     Claim {},
+    RegisterPubkey {
+        pubkey: Binary,
+    },
+    Relay(RelayParams),
+    GrantTrader {
+        grantee: Addr,
+        expires: Option<Timestamp>,
+        max_quote_spend: Option<Uint128>,
+        allowed_actions: Vec<TradeKind>,
+    },
+    Revoke {
+        grantee: Addr,
+    },
+    GrantRole {
+        role: Role,
+        address: Addr,
+    },
+    RevokeRole {
+        role: Role,
+        address: Addr,
+    },
+    SetFees(FeeInitArgs),
+    UpdateConfig(Config),
+    Resolve {
+        pool_id: PoolId,
+    },
+    Pause {
+        target: PauseTarget,
+    },
+    Unpause {
+        target: PauseTarget,
+    },
 }
 
 #[cw_serde]
 pub enum QueryMsg {
     Config {},
     Pools {},
-    Trader { address: Addr },
+    Trader {
+        address: Addr,
+    },
+    Signer {
+        address: Addr,
+    },
+    Grants {
+        granter: Addr,
+    },
+    RoleMembers {
+        role: Role,
+    },
+    PauseStatus {},
+    SimulateBuyAgainst {
+        pool_id: PoolId,
+        amount: Uint128,
+    },
+    QuoteBalance {
+        address: Addr,
+    },
+    Solvency {},
+    Positions {
+        address: Addr,
+    },
+    Payouts {
+        address: Addr,
+    },
+    Odds {},
+    Candles {
+        pool_id: PoolId,
+        resolution: Resolution,
+        start: Option<Timestamp>,
+        end: Option<Timestamp>,
+        limit: Option<u32>,
+    },
+    ProbabilityHistory {
+        start: Option<Timestamp>,
+        end: Option<Timestamp>,
+        interval: Option<u64>,
+        limit: Option<u32>,
+    },
+    RecentTrades {
+        since_seq: Option<u64>,
+        limit: Option<u32>,
+    },
+    SwapFlows {},
+    Holders {
+        pool_id: PoolId,
+        start_after: Option<Addr>,
+        limit: Option<u32>,
+    },
+    Leaderboard {
+        by: LeaderboardMetric,
+        limit: Option<u32>,
+    },
+    PoolStats {
+        pool_id: PoolId,
+    },
+    TradeHistory {
+        address: Addr,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
 }
 
 #[cw_serde]
-pub struct MigrateMsg {}
+pub struct MigrateMsg {
+    /// Address added to the admin role. Required when migrating from a version
+    /// without roles, which has no admin to carry over.
+    pub admin: Option<Addr>,
+}
 
 #[cw_serde]
 pub struct SwapParams {
@@ -64,6 +178,8 @@ pub struct SwapParams {
     pub to_pool: PoolId,
     pub from_pool: PoolId,
     pub from_amount: Uint128,
+    pub deadline: Option<Timestamp>,
+    pub valid_until_height: Option<u64>,
 }
 
 #[cw_serde]
@@ -72,16 +188,259 @@ pub struct PoolAmount {
     pub amount: Uint128,
 }
 
+#[cw_serde]
+pub struct PoolWeight {
+    pub pool_id: PoolId,
+    pub weight: u32,
+}
+
+/// Swap out of one or more source pools and spread the combined quote, net of
+/// a single swap fee, over one or more target pools by weight.
+#[cw_serde]
+pub struct SwapMultiParams {
+    pub initiator: Option<Addr>,
+    pub from: Vec<PoolAmount>,
+    pub to: Vec<PoolWeight>,
+    pub deadline: Option<Timestamp>,
+    pub valid_until_height: Option<u64>,
+}
+
 #[cw_serde]
 pub struct SellParams {
     pub initiator: Option<Addr>,
     pub amounts: Vec<PoolAmount>,
+    pub deadline: Option<Timestamp>,
+    pub valid_until_height: Option<u64>,
+    pub use_internal_balance: Option<bool>,
+}
+
+/// Sell the trader's entire balance in each of the given pools, or in every
+/// pool if none are given, paying out at least min_total_out after fees.
+#[cw_serde]
+pub struct SellAllParams {
+    pub initiator: Option<Addr>,
+    pub pools: Option<Vec<PoolId>>,
+    pub min_total_out: Uint128,
+    pub deadline: Option<Timestamp>,
+    pub valid_until_height: Option<u64>,
+    pub use_internal_balance: Option<bool>,
 }
 
 #[cw_serde]
 pub struct BuyParams {
     pub initiator: Option<Addr>,
     pub amounts: Vec<PoolAmount>,
+    pub deadline: Option<Timestamp>,
+    pub valid_until_height: Option<u64>,
+    pub use_internal_balance: Option<bool>,
+}
+
+/// Privileged roles. Each role may have multiple members. Admins manage role
+/// membership, relayers relay signed intents, resolvers declare the
+/// winning pool, pausers halt trading and fee managers update fees.
+#[cw_serde]
+#[derive(Copy)]
+pub enum Role {
+    Admin,
+    Relayer,
+    Resolver,
+    Pauser,
+    FeeManager,
+}
+
+impl Role {
+    pub fn to_key(&self) -> &'static str {
+        match self {
+            Self::Admin => "admin",
+            Self::Relayer => "relayer",
+            Self::Resolver => "resolver",
+            Self::Pauser => "pauser",
+            Self::FeeManager => "fee_manager",
+        }
+    }
+}
+
+/// What a pauser halts: trading in every pool, trading in specific pools, or
+/// claims. Claims are only halted by the Claims target.
+#[cw_serde]
+pub enum PauseTarget {
+    Market,
+    Pools(Vec<PoolId>),
+    Claims,
+}
+
+/// Time span covered by each OHLC bar.
+#[cw_serde]
+#[derive(Copy)]
+pub enum Resolution {
+    OneMinute,
+    FiveMinutes,
+    OneHour,
+    OneDay,
+}
+
+impl Resolution {
+    pub const ALL: [Resolution; 4] = [
+        Resolution::OneMinute,
+        Resolution::FiveMinutes,
+        Resolution::OneHour,
+        Resolution::OneDay,
+    ];
+
+    pub fn seconds(&self) -> u32 {
+        match self {
+            Self::OneMinute => 60,
+            Self::FiveMinutes => 300,
+            Self::OneHour => 3_600,
+            Self::OneDay => 86_400,
+        }
+    }
+}
+
+/// Kinds of trades that a granter can delegate to a grantee.
+#[cw_serde]
+#[derive(Copy)]
+pub enum TradeKind {
+    Buy,
+    Sell,
+    Swap,
+}
+
+/// Ranking of traders by total quote traded, total quote claimed or the size
+/// of their positions in a given pool.
+#[cw_serde]
+#[derive(Copy)]
+pub enum LeaderboardMetric {
+    Volume,
+    Claimed,
+    Position { pool_id: PoolId },
+}
+
+/// Kinds of trades recorded in trade histories and the global trade feed.
+#[cw_serde]
+#[derive(Copy)]
+pub enum TradeAction {
+    Buy,
+    Sell,
+    Swap,
+    Claim,
+    MintSet,
+    RedeemSet,
+}
+
+/// Trade action that a user authorizes by signing a TradeIntent.
+#[cw_serde]
+pub enum IntentAction {
+    Buy(BuyParams),
+    Sell(SellParams),
+    Swap(SwapParams),
+}
+
+#[cw_serde]
+pub struct TradeIntent {
+    pub signer: Addr,
+    pub nonce: u64,
+    pub expires: Timestamp,
+    pub action: IntentAction,
+}
+
+/// Document whose JSON serialization is hashed (sha256) and signed by the
+/// intent's signer. Binding the chain ID and contract address prevents an
+/// intent from being replayed against another deployment.
+#[cw_serde]
+pub struct IntentSignDoc {
+    pub chain_id: String,
+    pub contract: Addr,
+    pub intent: TradeIntent,
+}
+
+#[cw_serde]
+pub struct RelayParams {
+    pub intent: TradeIntent,
+    pub signature: Binary,
+}
+
+/// Buy exactly base_out of a pool's base, paying at most max_quote_in
+/// (including fees). Unused quote funds are refunded.
+#[cw_serde]
+pub struct BuyExactOutParams {
+    pub initiator: Option<Addr>,
+    pub pool_id: PoolId,
+    pub base_out: Uint128,
+    pub max_quote_in: Uint128,
+    pub deadline: Option<Timestamp>,
+    pub valid_until_height: Option<u64>,
+    pub use_internal_balance: Option<bool>,
+}
+
+/// Sell as much of a pool's base as required to receive exactly quote_out
+/// (after fees), selling at most max_base_in.
+#[cw_serde]
+pub struct SellExactOutParams {
+    pub initiator: Option<Addr>,
+    pub pool_id: PoolId,
+    pub quote_out: Uint128,
+    pub max_base_in: Uint128,
+    pub deadline: Option<Timestamp>,
+    pub valid_until_height: Option<u64>,
+    pub use_internal_balance: Option<bool>,
+}
+
+/// A single leg of a batch. Buy amounts are in quote (including fees), sell
+/// and swap amounts are in the base of the pool being sold.
+#[cw_serde]
+pub enum TradeOp {
+    Buy {
+        pool_id: PoolId,
+        amount: Uint128,
+    },
+    Sell {
+        pool_id: PoolId,
+        amount: Uint128,
+    },
+    Swap {
+        from_pool: PoolId,
+        to_pool: PoolId,
+        amount: Uint128,
+    },
+}
+
+/// Execute trade legs in order within one transaction. Quote flows are
+/// netted, so attached funds only need to cover buys in excess of sell
+/// proceeds, and any surplus is paid out in a single transfer.
+#[cw_serde]
+pub struct BatchParams {
+    pub initiator: Option<Addr>,
+    pub ops: Vec<TradeOp>,
+    pub min_final_balances: Vec<PoolAmount>,
+    pub deadline: Option<Timestamp>,
+    pub valid_until_height: Option<u64>,
+    pub use_internal_balance: Option<bool>,
+}
+
+/// Mint or redeem amount complete sets, where a complete set is one unit of
+/// position in every pool, backed by one unit of quote.
+#[cw_serde]
+pub struct CompleteSetParams {
+    pub initiator: Option<Addr>,
+    pub amount: Uint128,
+    pub deadline: Option<Timestamp>,
+    pub valid_until_height: Option<u64>,
+    pub use_internal_balance: Option<bool>,
+}
+
+/// Bet against pool_id by buying into every other pool in proportion to their
+/// current prices. For BuyAgainst, amount is quote (including fees). For
+/// SellAgainst, amount is the number of the position's shares to unwind, where
+/// shares are issued one per unit of quote paid by BuyAgainst.
+#[cw_serde]
+pub struct AgainstParams {
+    pub initiator: Option<Addr>,
+    pub pool_id: PoolId,
+    pub amount: Uint128,
+    pub deadline: Option<Timestamp>,
+    pub valid_until_height: Option<u64>,
+    pub use_internal_balance: Option<bool>,
 }
 
 #[cw_serde]
@@ -97,6 +456,9 @@ pub struct ConfigResponse(pub Config);
 pub struct PoolStats {
     pub num_buys: u32,
     pub num_sells: u32,
+    #[serde(default)]
+    pub num_swaps: u32,
+    /// Number of distinct accounts that have ever held a position in the pool.
     pub num_traders: u32,
     pub quote_amount_in: Uint256,
     pub quote_amount_out: Uint256,
@@ -123,6 +485,11 @@ pub struct PoolBizObject {
     pub winner: bool,
     pub offset: Uint128,
     pub supply: Uint128,
+    pub k: Uint256,
+    pub dust: Uint256,
+    pub probability: Decimal256,
+    pub decimal_odds: Decimal256,
+    pub payout_multiple: Decimal256,
     pub stats: PoolStats,
 }
 
@@ -130,6 +497,7 @@ pub struct PoolBizObject {
 pub struct PoolsResponse {
     pub pools: Vec<PoolBizObject>,
     pub stats: MarketStats,
+    pub set_collateral: Uint128,
 }
 
 #[cw_serde]
@@ -141,5 +509,203 @@ pub struct PoolBalance {
 #[cw_serde]
 pub struct TraderResponse {
     pub balances: Vec<PoolBalance>,
+    pub quote_balance: Uint128,
     pub stats: TraderStats,
+    pub against: Vec<AgainstPositionView>,
+}
+
+/// A trader's position against a pool along with the quote its legs would
+/// currently sell for, before sell fees.
+#[cw_serde]
+pub struct AgainstPositionView {
+    pub pool_id: PoolId,
+    pub position: AgainstPosition,
+    pub quote_value: Uint128,
+}
+
+#[cw_serde]
+pub struct AgainstLeg {
+    pub pool_id: PoolId,
+    pub quote_amount: Uint128,
+    pub base_amount: Uint128,
+}
+
+#[cw_serde]
+pub struct SimulateAgainstResponse {
+    pub legs: Vec<AgainstLeg>,
+    pub fee_amount: Uint128,
+}
+
+#[cw_serde]
+pub struct SignerResponse {
+    pub pubkey: Option<Binary>,
+    pub nonce: u64,
+}
+
+#[cw_serde]
+pub struct GrantView {
+    pub grantee: Addr,
+    pub grant: TraderGrant,
+}
+
+#[cw_serde]
+pub struct GrantsResponse {
+    pub grants: Vec<GrantView>,
+}
+
+#[cw_serde]
+pub struct RoleMembersResponse {
+    pub role: Role,
+    pub members: Vec<Addr>,
+}
+
+#[cw_serde]
+pub struct PauseStatusResponse {
+    pub market: bool,
+    pub claims: bool,
+    pub pools: Vec<PoolId>,
+}
+
+#[cw_serde]
+pub struct QuoteBalanceResponse {
+    pub balance: Uint128,
+}
+
+/// The contract's quote balance compared with what it owes: unclaimed winnings
+/// and traders' internal quote balances.
+#[cw_serde]
+pub struct SolvencyResponse {
+    pub balance: Uint128,
+    pub liabilities: Uint128,
+    pub is_solvent: bool,
+}
+
+/// A trader's position in a pool, marked to the pool's current quote price and
+/// to the amount it would claim if the pool won. Prices are per whole unit of
+/// base, scaled by quote decimals.
+#[cw_serde]
+pub struct PositionView {
+    pub pool_id: PoolId,
+    pub balance: Uint128,
+    pub cost_basis: Uint128,
+    pub avg_entry_price: Uint128,
+    pub realized_pnl: Int256,
+    pub price: Uint128,
+    pub market_value: Uint128,
+    pub unrealized_pnl: Int256,
+    pub claim_value: Uint128,
+}
+
+#[cw_serde]
+pub struct PositionsResponse {
+    pub positions: Vec<PositionView>,
+}
+
+/// What a trader would claim if a pool won. The claim amount is net of the
+/// claim fee, as is the payout multiple: quote paid per unit of position.
+#[cw_serde]
+pub struct PayoutView {
+    pub pool_id: PoolId,
+    pub balance: Uint128,
+    pub claim_amount: Uint128,
+    pub fee_amount: Uint128,
+    pub payout_multiple: Decimal256,
+}
+
+/// Implied probability of a pool winning, normalized across all pools, with
+/// the corresponding decimal odds (1 / probability) and the parimutuel payout
+/// multiple per unit of position.
+#[cw_serde]
+pub struct PoolOdds {
+    pub pool_id: PoolId,
+    pub probability: Decimal256,
+    pub decimal_odds: Decimal256,
+    pub payout_multiple: Decimal256,
+}
+
+#[cw_serde]
+pub struct CandlesResponse {
+    pub candles: Vec<OhlcBar>,
+}
+
+#[cw_serde]
+pub struct ProbabilityHistoryResponse {
+    pub snapshots: Vec<ProbabilitySnapshot>,
+}
+
+#[cw_serde]
+pub struct TradeHistoryEntry {
+    pub index: u64,
+    pub trade: TradeRecord,
+}
+
+#[cw_serde]
+pub struct TradeHistoryResponse {
+    pub trades: Vec<TradeHistoryEntry>,
+}
+
+/// Swap flow from one pool into another, where stats.n is the number of swaps
+/// and in_amount and out_amount are the base amounts into from_pool_id and out
+/// of to_pool_id.
+#[cw_serde]
+pub struct SwapFlow {
+    pub from_pool_id: PoolId,
+    pub to_pool_id: PoolId,
+    pub stats: SwapStats,
+}
+
+#[cw_serde]
+pub struct SwapFlowsResponse {
+    pub flows: Vec<SwapFlow>,
+}
+
+#[cw_serde]
+pub struct PoolStatsResponse {
+    pub pool_id: PoolId,
+    pub reserves: PoolReserves,
+    pub supply: Uint128,
+    pub price: Uint128,
+    pub probability: Decimal256,
+    pub stats: PoolStats,
+}
+
+#[cw_serde]
+pub struct Holder {
+    pub address: Addr,
+    pub balance: Uint128,
+}
+
+#[cw_serde]
+pub struct HoldersResponse {
+    pub holders: Vec<Holder>,
+}
+
+/// Leaderboard entry, with pool_id set when ranking positions.
+#[cw_serde]
+pub struct LeaderboardEntry {
+    pub address: Addr,
+    pub pool_id: Option<PoolId>,
+    pub amount: Uint128,
+}
+
+#[cw_serde]
+pub struct LeaderboardResponse {
+    pub entries: Vec<LeaderboardEntry>,
+}
+
+#[cw_serde]
+pub struct RecentTradesResponse {
+    pub trades: Vec<SequencedTrade>,
+    pub last_seq: u64,
+}
+
+#[cw_serde]
+pub struct OddsResponse {
+    pub pools: Vec<PoolOdds>,
+}
+
+#[cw_serde]
+pub struct PayoutsResponse {
+    pub payouts: Vec<PayoutView>,
+    pub has_claimed: bool,
 }
@@ -0,0 +1,48 @@
+use crate::error::ContractError;
+use cosmwasm_std::{Order, Timestamp};
+use cw_storage_plus::Bound;
+
+use crate::{
+    msg::{CandlesResponse, Resolution},
+    state::storage::{PoolId, POOL_CANDLES, POOL_OHLC_BARS},
+};
+
+use super::ReadonlyContext;
+
+const DEFAULT_LIMIT: u32 = 100;
+const MAX_LIMIT: u32 = 500;
+
+/// Read a pool's bars at the given resolution in ascending order of time,
+/// from start (inclusive) to end (exclusive).
+pub fn query_candles(
+    ctx: ReadonlyContext,
+    pool_id: PoolId,
+    resolution: Resolution,
+    start: Option<Timestamp>,
+    end: Option<Timestamp>,
+    limit: Option<u32>,
+) -> Result<CandlesResponse, ContractError> {
+    let ReadonlyContext { deps, .. } = ctx;
+
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let min = start.map(|t| Bound::inclusive(t.seconds()));
+    let max = end.map(|t| Bound::exclusive(t.seconds()));
+
+    let candles = if let Resolution::OneMinute = resolution {
+        POOL_OHLC_BARS
+            .prefix(pool_id)
+            .range(deps.storage, min, max, Order::Ascending)
+            .take(limit)
+            .map(|result| result.map(|(_, bar)| bar))
+            .collect::<Result<Vec<_>, _>>()?
+    } else {
+        POOL_CANDLES
+            .prefix((pool_id, resolution.seconds()))
+            .range(deps.storage, min, max, Order::Ascending)
+            .take(limit)
+            .map(|result| result.map(|(_, bar)| bar))
+            .collect::<Result<Vec<_>, _>>()?
+    };
+
+    Ok(CandlesResponse { candles })
+}
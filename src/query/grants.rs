@@ -0,0 +1,28 @@
+use crate::error::ContractError;
+use cosmwasm_std::{Addr, Order};
+
+use crate::{
+    msg::{GrantView, GrantsResponse},
+    state::storage::TRADER_GRANTS,
+};
+
+use super::ReadonlyContext;
+
+pub fn query_grants(
+    ctx: ReadonlyContext,
+    granter: Addr,
+) -> Result<GrantsResponse, ContractError> {
+    let ReadonlyContext { deps, .. } = ctx;
+
+    let mut grants: Vec<GrantView> = Vec::with_capacity(2);
+
+    for result in TRADER_GRANTS
+        .prefix(&granter)
+        .range(deps.storage, None, None, Order::Ascending)
+    {
+        let (grantee, grant) = result?;
+        grants.push(GrantView { grantee, grant });
+    }
+
+    Ok(GrantsResponse { grants })
+}
@@ -0,0 +1,65 @@
+use crate::error::ContractError;
+use cosmwasm_std::{Addr, Order};
+use cw_storage_plus::Bound;
+
+use crate::{
+    msg::{Holder, HoldersResponse},
+    state::storage::{PoolId, POOL_ACCOUNTS},
+};
+
+use super::ReadonlyContext;
+
+const DEFAULT_LIMIT: u32 = 100;
+const MAX_LIMIT: u32 = 500;
+
+/// Read accounts with a nonzero balance in a pool, in descending order of
+/// balance, starting after the given address.
+pub fn query_holders(
+    ctx: ReadonlyContext,
+    pool_id: PoolId,
+    start_after: Option<Addr>,
+    limit: Option<u32>,
+) -> Result<HoldersResponse, ContractError> {
+    let ReadonlyContext { deps, .. } = ctx;
+
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    // Resume below the previous page's last holder at its current balance
+    let max = match start_after {
+        Some(address) => {
+            let balance = POOL_ACCOUNTS
+                .may_load(deps.storage, (&address, pool_id))?
+                .map(|account| account.balance)
+                .unwrap_or_default();
+            Some(Bound::exclusive((balance.u128(), (address, pool_id))))
+        },
+        None => None,
+    };
+
+    let holders = POOL_ACCOUNTS
+        .idx
+        .pool_balance
+        .sub_prefix(pool_id)
+        .range(
+            deps.storage,
+            Some(min_nonzero_balance(pool_id)),
+            max,
+            Order::Descending,
+        )
+        .take(limit)
+        .map(|result| {
+            result.map(|((address, _), account)| Holder {
+                address,
+                balance: account.balance,
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(HoldersResponse { holders })
+}
+
+/// Lower bound of a pool's balance index that excludes accounts that have
+/// exited the pool: a balance of 1 with the lowest possible address.
+pub fn min_nonzero_balance<'a>(pool_id: PoolId) -> Bound<'a, (u128, (Addr, PoolId))> {
+    Bound::inclusive((1u128, (Addr::unchecked(""), pool_id)))
+}
@@ -0,0 +1,86 @@
+use crate::error::ContractError;
+use cosmwasm_std::{Order, StdResult};
+
+use crate::{
+    msg::{LeaderboardEntry, LeaderboardMetric, LeaderboardResponse},
+    state::storage::{POOL_ACCOUNTS, TRADER_INFOS},
+};
+
+use super::{holders::min_nonzero_balance, ReadonlyContext};
+
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 100;
+
+/// Read the top traders by the given metric in descending order, leaving out
+/// those with nothing to rank.
+pub fn query_leaderboard(
+    ctx: ReadonlyContext,
+    by: LeaderboardMetric,
+    limit: Option<u32>,
+) -> Result<LeaderboardResponse, ContractError> {
+    let ReadonlyContext { deps, .. } = ctx;
+
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    let entries: Vec<LeaderboardEntry> = match by {
+        LeaderboardMetric::Volume => TRADER_INFOS
+            .idx
+            .volume
+            .range(deps.storage, None, None, Order::Descending)
+            .map(|result| {
+                result.map(|(address, info)| LeaderboardEntry {
+                    address,
+                    pool_id: None,
+                    amount: info
+                        .stats
+                        .quote_amount_in
+                        .saturating_add(info.stats.quote_amount_out),
+                })
+            })
+            .take_while(is_ranked)
+            .take(limit)
+            .collect::<Result<_, _>>()?,
+        LeaderboardMetric::Claimed => TRADER_INFOS
+            .idx
+            .claimed
+            .range(deps.storage, None, None, Order::Descending)
+            .map(|result| {
+                result.map(|(address, info)| LeaderboardEntry {
+                    address,
+                    pool_id: None,
+                    amount: info.stats.amount_claimed,
+                })
+            })
+            .take_while(is_ranked)
+            .take(limit)
+            .collect::<Result<_, _>>()?,
+        LeaderboardMetric::Position { pool_id } => POOL_ACCOUNTS
+            .idx
+            .pool_balance
+            .sub_prefix(pool_id)
+            .range(
+                deps.storage,
+                Some(min_nonzero_balance(pool_id)),
+                None,
+                Order::Descending,
+            )
+            .map(|result| {
+                result.map(|((address, pool_id), account)| LeaderboardEntry {
+                    address,
+                    pool_id: Some(pool_id),
+                    amount: account.balance,
+                })
+            })
+            .take_while(is_ranked)
+            .take(limit)
+            .collect::<Result<_, _>>()?,
+    };
+
+    Ok(LeaderboardResponse { entries })
+}
+
+/// Entries are read in descending order of amount, so ranking stops at the
+/// first zero amount.
+fn is_ranked(result: &StdResult<LeaderboardEntry>) -> bool {
+    !matches!(result, Ok(entry) if entry.amount.is_zero())
+}
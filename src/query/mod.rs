@@ -1,5 +1,23 @@
+pub mod candles;
 pub mod config;
+pub mod grants;
+pub mod holders;
+pub mod leaderboard;
+pub mod odds;
+pub mod pause_status;
+pub mod payouts;
+pub mod pool_stats;
 pub mod pools;
+pub mod positions;
+pub mod probability_history;
+pub mod quote_balance;
+pub mod recent_trades;
+pub mod roles;
+pub mod signer;
+pub mod simulate_against;
+pub mod solvency;
+pub mod swap_flows;
+pub mod trade_history;
 pub mod trader;
 
 use cosmwasm_std::{Deps, Env};
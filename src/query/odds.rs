@@ -0,0 +1,12 @@
+use crate::error::ContractError;
+
+use crate::{msg::OddsResponse, state::utils::calc_odds};
+
+use super::ReadonlyContext;
+
+pub fn query_odds(ctx: ReadonlyContext) -> Result<OddsResponse, ContractError> {
+    let ReadonlyContext { deps, .. } = ctx;
+    Ok(OddsResponse {
+        pools: calc_odds(deps.storage)?,
+    })
+}
@@ -0,0 +1,23 @@
+use crate::error::ContractError;
+use cosmwasm_std::Order;
+
+use crate::{
+    msg::PauseStatusResponse,
+    state::storage::{PoolId, CLAIMS_PAUSED, MARKET_PAUSED, PAUSED_POOLS},
+};
+
+use super::ReadonlyContext;
+
+pub fn query_pause_status(ctx: ReadonlyContext) -> Result<PauseStatusResponse, ContractError> {
+    let ReadonlyContext { deps, .. } = ctx;
+
+    let pools = PAUSED_POOLS
+        .keys(deps.storage, None, None, Order::Ascending)
+        .collect::<Result<Vec<PoolId>, _>>()?;
+
+    Ok(PauseStatusResponse {
+        market: MARKET_PAUSED.may_load(deps.storage)?.unwrap_or(false),
+        claims: CLAIMS_PAUSED.may_load(deps.storage)?.unwrap_or(false),
+        pools,
+    })
+}
@@ -0,0 +1,48 @@
+use crate::error::ContractError;
+use crate::math::sub_u128;
+use cosmwasm_std::{Addr, Order};
+
+use crate::{
+    msg::{PayoutView, PayoutsResponse},
+    state::{
+        storage::{HAS_CLAIMED, POOLS, POOL_ACCOUNTS},
+        utils::{calc_claim, calc_payout_multiple},
+    },
+};
+
+use super::ReadonlyContext;
+
+pub fn query_payouts(
+    ctx: ReadonlyContext,
+    address: Addr,
+) -> Result<PayoutsResponse, ContractError> {
+    let ReadonlyContext { deps, .. } = ctx;
+
+    let mut payouts: Vec<PayoutView> = Vec::with_capacity(2);
+
+    for result in POOLS.keys(deps.storage, None, None, Order::Ascending) {
+        let pool_id = result?;
+
+        let balance = POOL_ACCOUNTS
+            .may_load(deps.storage, (&address, pool_id))?
+            .map(|account| account.balance)
+            .unwrap_or_default();
+
+        let (claim_amount, fee_amount) = calc_claim(deps.storage, pool_id, balance)?;
+
+        payouts.push(PayoutView {
+            pool_id,
+            balance,
+            claim_amount: sub_u128(claim_amount, fee_amount)?,
+            fee_amount,
+            payout_multiple: calc_payout_multiple(deps.storage, pool_id)?,
+        });
+    }
+
+    Ok(PayoutsResponse {
+        payouts,
+        has_claimed: HAS_CLAIMED
+            .may_load(deps.storage, &address)?
+            .unwrap_or(false),
+    })
+}
@@ -0,0 +1,43 @@
+use crate::error::ContractError;
+
+use crate::{
+    msg::PoolStatsResponse,
+    state::{
+        models::Pool,
+        storage::{PoolId, POOLS, POOL_STATS, QUOTE_DECIMALS},
+        utils::calc_probabilities,
+    },
+};
+
+use super::ReadonlyContext;
+
+/// Read a single pool's reserves, price, implied probability and stats.
+pub fn query_pool_stats(
+    ctx: ReadonlyContext,
+    pool_id: PoolId,
+) -> Result<PoolStatsResponse, ContractError> {
+    let ReadonlyContext { deps, .. } = ctx;
+
+    if !POOLS.has(deps.storage, pool_id) {
+        return Err(ContractError::ValidationError {
+            msg: format!("pool {} does not exist", pool_id),
+        });
+    }
+
+    let pool = Pool::load(deps.storage, pool_id)?;
+
+    let probability = calc_probabilities(deps.storage)?
+        .into_iter()
+        .find(|(id, _)| *id == pool_id)
+        .map(|(_, probability)| probability)
+        .unwrap_or_default();
+
+    Ok(PoolStatsResponse {
+        pool_id,
+        price: pool.calc_quote_price(QUOTE_DECIMALS.load(deps.storage)?)?,
+        reserves: pool.reserves,
+        supply: pool.supply,
+        probability,
+        stats: POOL_STATS.load(deps.storage, pool_id)?,
+    })
+}
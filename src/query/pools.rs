@@ -5,7 +5,8 @@ use crate::{
     msg::{PoolBizObject, PoolsResponse},
     state::{
         models::{Pool, PoolInfo},
-        storage::{PoolId, MARKET_STATS, POOLS, POOL_INFOS, POOL_STATS},
+        storage::{MARKET_STATS, POOLS, POOL_INFOS, POOL_STATS, SET_COLLATERAL, WINNING_POOL_ID},
+        utils::calc_odds,
     },
 };
 
@@ -14,16 +15,19 @@ use super::ReadonlyContext;
 pub fn query_pools(ctx: ReadonlyContext) -> Result<PoolsResponse, ContractError> {
     let ReadonlyContext { deps, .. } = ctx;
 
-    // TODO: get this by querying the associated jury contract!
-    let winning_pool_id: PoolId = 0;
+    let winning_pool_id = WINNING_POOL_ID.may_load(deps.storage)?;
 
     let stats = MARKET_STATS.load(deps.storage)?;
 
+    let odds = calc_odds(deps.storage)?;
+
     let mut pools: Vec<PoolBizObject> = Vec::with_capacity(2);
 
-    for result in POOLS
+    for (result, pool_odds) in POOLS
         .range(deps.storage, None, None, Order::Ascending)
         .collect::<Vec<StdResult<_>>>()
+        .into_iter()
+        .zip(odds)
     {
         let (
             pool_id,
@@ -31,7 +35,8 @@ pub fn query_pools(ctx: ReadonlyContext) -> Result<PoolsResponse, ContractError>
                 reserves,
                 offset,
                 supply,
-                ..
+                k,
+                dust,
             },
         ) = result?;
 
@@ -46,7 +51,7 @@ pub fn query_pools(ctx: ReadonlyContext) -> Result<PoolsResponse, ContractError>
 
         pools.push(PoolBizObject {
             id: pool_id,
-            winner: winning_pool_id == pool_id,
+            winner: winning_pool_id == Some(pool_id),
             stats: pool_stats,
             symbol,
             name,
@@ -55,8 +60,17 @@ pub fn query_pools(ctx: ReadonlyContext) -> Result<PoolsResponse, ContractError>
             reserves,
             offset,
             supply,
+            k,
+            dust,
+            probability: pool_odds.probability,
+            decimal_odds: pool_odds.decimal_odds,
+            payout_multiple: pool_odds.payout_multiple,
         });
     }
 
-    Ok(PoolsResponse { pools, stats })
+    Ok(PoolsResponse {
+        set_collateral: SET_COLLATERAL.may_load(deps.storage)?.unwrap_or_default(),
+        pools,
+        stats,
+    })
 }
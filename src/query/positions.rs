@@ -0,0 +1,67 @@
+use crate::error::ContractError;
+use crate::math::{mul_ratio_u128, sub_i256, sub_u128};
+use cosmwasm_std::{Addr, Order, Uint128};
+
+use crate::{
+    msg::{PositionView, PositionsResponse},
+    state::{
+        storage::{POOLS, POOL_ACCOUNTS, POSITION_COSTS, QUOTE_DECIMALS},
+        utils::calc_claim,
+    },
+};
+
+use super::ReadonlyContext;
+
+pub fn query_positions(
+    ctx: ReadonlyContext,
+    address: Addr,
+) -> Result<PositionsResponse, ContractError> {
+    let ReadonlyContext { deps, .. } = ctx;
+
+    let quote_decimals = QUOTE_DECIMALS.load(deps.storage)?;
+    let price_scale = Uint128::from(10u128.pow(quote_decimals as u32));
+
+    let mut positions: Vec<PositionView> = Vec::with_capacity(2);
+
+    for result in POOLS.range(deps.storage, None, None, Order::Ascending) {
+        let (pool_id, pool) = result?;
+
+        let maybe_account = POOL_ACCOUNTS.may_load(deps.storage, (&address, pool_id))?;
+        let maybe_cost = POSITION_COSTS.may_load(deps.storage, (&address, pool_id))?;
+        if maybe_account.is_none() && maybe_cost.is_none() {
+            continue;
+        }
+
+        let balance = maybe_account
+            .map(|account| account.balance)
+            .unwrap_or_default();
+        let position_cost = maybe_cost.unwrap_or_default();
+
+        let price = pool.calc_quote_price(quote_decimals)?;
+        let market_value = mul_ratio_u128(balance, price, price_scale)?;
+
+        let avg_entry_price = if balance.is_zero() {
+            Uint128::zero()
+        } else {
+            mul_ratio_u128(position_cost.cost_basis, price_scale, balance)?
+        };
+
+        // Amount the position would claim, net of fees, if this pool won
+        let (claim_amount, fee_amount) = calc_claim(deps.storage, pool_id, balance)?;
+        let claim_value = sub_u128(claim_amount, fee_amount)?;
+
+        positions.push(PositionView {
+            pool_id,
+            balance,
+            cost_basis: position_cost.cost_basis,
+            avg_entry_price,
+            realized_pnl: position_cost.realized_pnl,
+            price,
+            market_value,
+            unrealized_pnl: sub_i256(market_value, position_cost.cost_basis)?,
+            claim_value,
+        });
+    }
+
+    Ok(PositionsResponse { positions })
+}
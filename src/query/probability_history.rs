@@ -0,0 +1,69 @@
+use crate::error::ContractError;
+use cosmwasm_std::{Order, Timestamp};
+use cw_storage_plus::Bound;
+
+use crate::{
+    msg::ProbabilityHistoryResponse,
+    state::{models::ProbabilitySnapshot, storage::PROBABILITY_SNAPSHOTS},
+};
+
+use super::ReadonlyContext;
+
+const DEFAULT_LIMIT: u32 = 100;
+const MAX_LIMIT: u32 = 500;
+
+/// Read implied probability snapshots in ascending order of time, from start
+/// (inclusive) to end (exclusive). When an interval (in seconds) is given,
+/// only the last snapshot in each interval is returned.
+pub fn query_probability_history(
+    ctx: ReadonlyContext,
+    start: Option<Timestamp>,
+    end: Option<Timestamp>,
+    interval: Option<u64>,
+    limit: Option<u32>,
+) -> Result<ProbabilityHistoryResponse, ContractError> {
+    let ReadonlyContext { deps, .. } = ctx;
+
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let interval = interval.unwrap_or(1).max(1);
+    let end = end.map(|t| t.seconds()).unwrap_or(u64::MAX);
+
+    let mut snapshots: Vec<ProbabilitySnapshot> = Vec::with_capacity(limit);
+    let mut cursor = start.map(|t| t.seconds()).unwrap_or_default();
+
+    // Read two snapshots per interval, the first to find the next interval
+    // with any snapshots and the second to take its last snapshot, so that
+    // reads are bounded by limit rather than by the number of snapshots.
+    while snapshots.len() < limit && cursor < end {
+        let next = PROBABILITY_SNAPSHOTS
+            .keys(
+                deps.storage,
+                Some(Bound::inclusive(cursor)),
+                Some(Bound::exclusive(end)),
+                Order::Ascending,
+            )
+            .next()
+            .transpose()?;
+        let Some(t) = next else {
+            break;
+        };
+
+        let interval_end = (t / interval).saturating_add(1).saturating_mul(interval);
+        let last = PROBABILITY_SNAPSHOTS
+            .range(
+                deps.storage,
+                Some(Bound::inclusive(t)),
+                Some(Bound::exclusive(interval_end.min(end))),
+                Order::Descending,
+            )
+            .next()
+            .transpose()?;
+        if let Some((_, snapshot)) = last {
+            snapshots.push(snapshot);
+        }
+
+        cursor = interval_end;
+    }
+
+    Ok(ProbabilityHistoryResponse { snapshots })
+}
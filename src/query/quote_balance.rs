@@ -0,0 +1,19 @@
+use crate::error::ContractError;
+use cosmwasm_std::Addr;
+
+use crate::{msg::QuoteBalanceResponse, state::storage::QUOTE_ACCOUNTS};
+
+use super::ReadonlyContext;
+
+pub fn query_quote_balance(
+    ctx: ReadonlyContext,
+    address: Addr,
+) -> Result<QuoteBalanceResponse, ContractError> {
+    let ReadonlyContext { deps, .. } = ctx;
+    Ok(QuoteBalanceResponse {
+        balance: QUOTE_ACCOUNTS
+            .may_load(deps.storage, &address)?
+            .map(|account| account.balance)
+            .unwrap_or_default(),
+    })
+}
@@ -0,0 +1,42 @@
+use crate::error::ContractError;
+
+use crate::{
+    msg::RecentTradesResponse,
+    state::{
+        models::RECENT_TRADES_CAPACITY,
+        storage::{RECENT_TRADES, TRADE_SEQ},
+    },
+};
+
+use super::ReadonlyContext;
+
+const DEFAULT_LIMIT: u32 = 100;
+const MAX_LIMIT: u32 = 500;
+
+/// Read trades from the global trade feed in ascending order of sequence
+/// number, starting after since_seq. If trades after since_seq have already
+/// been evicted from the feed, the oldest retained trades are returned, which
+/// clients can detect as a gap before the first trade's seq.
+pub fn query_recent_trades(
+    ctx: ReadonlyContext,
+    since_seq: Option<u64>,
+    limit: Option<u32>,
+) -> Result<RecentTradesResponse, ContractError> {
+    let ReadonlyContext { deps, .. } = ctx;
+
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let last_seq = TRADE_SEQ.may_load(deps.storage)?.unwrap_or_default();
+    let oldest_seq = last_seq.saturating_sub(RECENT_TRADES_CAPACITY) + 1;
+    let first_seq = since_seq
+        .unwrap_or_default()
+        .saturating_add(1)
+        .max(oldest_seq);
+
+    // Every seq from oldest_seq to last_seq still occupies its slot
+    let trades = (first_seq..=last_seq)
+        .take(limit)
+        .map(|seq| RECENT_TRADES.load(deps.storage, seq % RECENT_TRADES_CAPACITY))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(RecentTradesResponse { trades, last_seq })
+}
@@ -0,0 +1,23 @@
+use crate::error::ContractError;
+use cosmwasm_std::{Addr, Order};
+
+use crate::{
+    msg::{Role, RoleMembersResponse},
+    state::storage::ROLE_MEMBERS,
+};
+
+use super::ReadonlyContext;
+
+pub fn query_role_members(
+    ctx: ReadonlyContext,
+    role: Role,
+) -> Result<RoleMembersResponse, ContractError> {
+    let ReadonlyContext { deps, .. } = ctx;
+
+    let members = ROLE_MEMBERS
+        .prefix(role.to_key())
+        .keys(deps.storage, None, None, Order::Ascending)
+        .collect::<Result<Vec<Addr>, _>>()?;
+
+    Ok(RoleMembersResponse { role, members })
+}
@@ -0,0 +1,22 @@
+use crate::error::ContractError;
+use cosmwasm_std::Addr;
+
+use crate::{
+    msg::SignerResponse,
+    state::storage::{INTENT_NONCES, TRADER_PUBKEYS},
+};
+
+use super::ReadonlyContext;
+
+pub fn query_signer(
+    ctx: ReadonlyContext,
+    address: Addr,
+) -> Result<SignerResponse, ContractError> {
+    let ReadonlyContext { deps, .. } = ctx;
+    Ok(SignerResponse {
+        pubkey: TRADER_PUBKEYS.may_load(deps.storage, &address)?,
+        nonce: INTENT_NONCES
+            .may_load(deps.storage, &address)?
+            .unwrap_or_default(),
+    })
+}
@@ -0,0 +1,43 @@
+use crate::error::ContractError;
+use crate::math::{add_u128, mul_pct_u128, sub_u128};
+use cosmwasm_std::Uint128;
+
+use crate::{
+    msg::{AgainstLeg, SimulateAgainstResponse},
+    state::{
+        models::{AgainstPosition, Pool},
+        storage::{PoolId, BUY_FEE_PCT},
+    },
+};
+
+use super::ReadonlyContext;
+
+pub fn query_simulate_buy_against(
+    ctx: ReadonlyContext,
+    pool_id: PoolId,
+    amount: Uint128,
+) -> Result<SimulateAgainstResponse, ContractError> {
+    let ReadonlyContext { deps, .. } = ctx;
+    let fee_pct = BUY_FEE_PCT.load(deps.storage)?;
+
+    let mut legs: Vec<AgainstLeg> = Vec::with_capacity(4);
+    let mut total_fee_amount = Uint128::zero();
+
+    for allocation in AgainstPosition::allocate(deps.storage, pool_id, amount)? {
+        let mut pool = Pool::load(deps.storage, allocation.pool_id)?;
+        let fee_amount = mul_pct_u128(allocation.amount, fee_pct)?;
+        let base_amount = pool.buy(sub_u128(allocation.amount, fee_amount)?)?;
+
+        total_fee_amount = add_u128(total_fee_amount, fee_amount)?;
+        legs.push(AgainstLeg {
+            pool_id: allocation.pool_id,
+            quote_amount: allocation.amount,
+            base_amount,
+        });
+    }
+
+    Ok(SimulateAgainstResponse {
+        legs,
+        fee_amount: total_fee_amount,
+    })
+}
@@ -0,0 +1,21 @@
+use crate::error::ContractError;
+
+use crate::{
+    msg::SolvencyResponse,
+    state::{storage::QUOTE_TOKEN, utils::calc_liabilities},
+};
+
+use super::ReadonlyContext;
+
+pub fn query_solvency(ctx: ReadonlyContext) -> Result<SolvencyResponse, ContractError> {
+    let ReadonlyContext { deps, env } = ctx;
+    let balance = QUOTE_TOKEN
+        .load(deps.storage)?
+        .query_balance(deps.querier, &env.contract.address)?;
+    let liabilities = calc_liabilities(deps.storage)?;
+    Ok(SolvencyResponse {
+        balance,
+        liabilities,
+        is_solvent: balance >= liabilities,
+    })
+}
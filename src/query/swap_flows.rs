@@ -0,0 +1,43 @@
+use crate::error::ContractError;
+use cosmwasm_std::{Order, Uint256};
+
+use crate::{
+    msg::{SwapFlow, SwapFlowsResponse, SwapStats},
+    state::storage::{PoolId, POOLS, SWAP_STATS},
+};
+
+use super::ReadonlyContext;
+
+/// Read swap flows between every ordered pair of distinct pools, including
+/// pairs that have never been swapped between.
+pub fn query_swap_flows(ctx: ReadonlyContext) -> Result<SwapFlowsResponse, ContractError> {
+    let ReadonlyContext { deps, .. } = ctx;
+
+    let pool_ids = POOLS
+        .keys(deps.storage, None, None, Order::Ascending)
+        .collect::<Result<Vec<PoolId>, _>>()?;
+
+    let mut flows: Vec<SwapFlow> = Vec::with_capacity(pool_ids.len() * pool_ids.len());
+
+    for from_pool_id in pool_ids.iter().copied() {
+        for to_pool_id in pool_ids.iter().copied() {
+            if from_pool_id == to_pool_id {
+                continue;
+            }
+            let stats = SWAP_STATS
+                .may_load(deps.storage, (from_pool_id, to_pool_id))?
+                .unwrap_or_else(|| SwapStats {
+                    n: 0,
+                    in_amount: Uint256::zero(),
+                    out_amount: Uint256::zero(),
+                });
+            flows.push(SwapFlow {
+                from_pool_id,
+                to_pool_id,
+                stats,
+            });
+        }
+    }
+
+    Ok(SwapFlowsResponse { flows })
+}
@@ -0,0 +1,36 @@
+use crate::error::ContractError;
+use cosmwasm_std::{Addr, Order};
+use cw_storage_plus::Bound;
+
+use crate::{
+    msg::{TradeHistoryEntry, TradeHistoryResponse},
+    state::storage::TRADE_HISTORY,
+};
+
+use super::ReadonlyContext;
+
+const DEFAULT_LIMIT: u32 = 50;
+const MAX_LIMIT: u32 = 200;
+
+/// Read a trader's retained trades, newest first, starting after the given
+/// trade index.
+pub fn query_trade_history(
+    ctx: ReadonlyContext,
+    address: Addr,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> Result<TradeHistoryResponse, ContractError> {
+    let ReadonlyContext { deps, .. } = ctx;
+
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let max = start_after.map(Bound::exclusive);
+
+    let trades = TRADE_HISTORY
+        .prefix(&address)
+        .range(deps.storage, None, max, Order::Descending)
+        .take(limit)
+        .map(|result| result.map(|(index, trade)| TradeHistoryEntry { index, trade }))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(TradeHistoryResponse { trades })
+}
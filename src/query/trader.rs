@@ -1,11 +1,12 @@
-use cosmwasm_std::{Addr, Order};
 use crate::error::ContractError;
+use crate::math::add_u128;
+use cosmwasm_std::{Addr, Order, Uint128};
 
 use crate::{
-    msg::{PoolBalance, TraderResponse},
+    msg::{AgainstPositionView, PoolBalance, TraderResponse},
     state::{
-        models::TraderInfo,
-        storage::{POOL_ACCOUNTS, TRADER_INFOS},
+        models::{Pool, TraderInfo},
+        storage::{AGAINST_POSITIONS, POOL_ACCOUNTS, QUOTE_ACCOUNTS, TRADER_INFOS},
     },
 };
 
@@ -33,5 +34,37 @@ pub fn query_trader(
         })
     }
 
-    Ok(TraderResponse { balances, stats })
+    // Collect positions against pools, valued by simulating a sale of each leg
+    let mut against: Vec<AgainstPositionView> = Vec::with_capacity(1);
+
+    for result in
+        AGAINST_POSITIONS
+            .prefix(&address)
+            .range(deps.storage, None, None, Order::Ascending)
+    {
+        let (pool_id, position) = result?;
+        let mut quote_value = Uint128::zero();
+        for leg in position.legs.iter() {
+            let mut pool = Pool::load(deps.storage, leg.pool_id)?;
+            // Legs too small to yield any quote are valued at zero
+            quote_value = add_u128(quote_value, pool.sell(leg.amount).unwrap_or_default())?;
+        }
+        against.push(AgainstPositionView {
+            pool_id,
+            position,
+            quote_value,
+        })
+    }
+
+    let quote_balance = QUOTE_ACCOUNTS
+        .may_load(deps.storage, &address)?
+        .map(|account| account.balance)
+        .unwrap_or_default();
+
+    Ok(TraderResponse {
+        balances,
+        quote_balance,
+        stats,
+        against,
+    })
 }
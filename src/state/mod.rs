@@ -3,19 +3,23 @@ pub mod storage;
 pub mod utils;
 
 use crate::{error::ContractError, math::mul_u256};
-use cosmwasm_std::{Response, Uint128, Uint256};
-use storage::{OPERATOR_ADDR, QUOTE_DECIMALS, QUOTE_SYMBOL, SELL_FEE_PCT};
+use cosmwasm_std::{Addr, DepsMut, Order, Response, Storage, Uint128, Uint256};
+use storage::{
+    LEGACY_OPERATOR_ADDR, QUOTE_BALANCES_TOTAL, QUOTE_DECIMALS, QUOTE_SYMBOL, ROLE_MEMBERS,
+    SELL_FEE_PCT, SET_COLLATERAL,
+};
 
 use crate::{
     execute::Context,
-    msg::{InstantiateMsg, PoolInitArgs, PoolStats},
+    msg::{InstantiateMsg, PoolInitArgs, PoolStats, Role},
 };
 
 use self::{
     models::{MarketStats, Pool, PoolInfo},
     storage::{
-        PoolId, AMOUNT_CLAIMED, BUY_FEE_PCT, FEE_MANAGER_ADDR, MARKET_STATS, POOLS, POOL_INFOS,
-        POOL_STATS, QUOTE_TOKEN, START_TIME, STOP_TIME, SWAP_FEE_PCT,
+        PoolId, AMOUNT_CLAIMED, BUY_FEE_PCT, CLAIM_FEE_PCT, CONFIG, FEE_MANAGER_ADDR, MARKET_STATS,
+        POOLS, POOL_ACCOUNTS, POOL_INFOS, POOL_STATS, QUOTE_TOKEN, START_TIME, STOP_TIME,
+        SWAP_FEE_PCT, TRADER_INFOS,
     },
 };
 
@@ -34,20 +38,29 @@ pub fn init(
         quote_symbol,
         operator,
         fees,
+        config,
     } = msg;
 
+    CONFIG.save(deps.storage, &config.clone().unwrap_or_default())?;
     AMOUNT_CLAIMED.save(deps.storage, &Uint128::zero())?;
+    SET_COLLATERAL.save(deps.storage, &Uint128::zero())?;
+    QUOTE_BALANCES_TOTAL.save(deps.storage, &Uint128::zero())?;
     QUOTE_TOKEN.save(deps.storage, quote_token)?;
     QUOTE_DECIMALS.save(deps.storage, quote_decimals)?;
     QUOTE_SYMBOL.save(deps.storage, quote_symbol)?;
     BUY_FEE_PCT.save(deps.storage, &fees.pct_buy)?;
     SELL_FEE_PCT.save(deps.storage, &fees.pct_sell)?;
     SWAP_FEE_PCT.save(deps.storage, &fees.pct_swap)?;
-    START_TIME.save(deps.storage, &start)?;
+    CLAIM_FEE_PCT.save(deps.storage, &fees.pct_claim.unwrap_or_default())?;
+    START_TIME.save(deps.storage, start)?;
     STOP_TIME.save(deps.storage, stop)?;
 
+    // The instantiator administers roles. The operator, if any, relays trades.
+    ROLE_MEMBERS.save(deps.storage, (Role::Admin.to_key(), &info.sender), &true)?;
+
     if let Some(operator) = operator {
-        OPERATOR_ADDR.save(deps.storage, &deps.api.addr_validate(operator.as_str())?)?;
+        let operator = deps.api.addr_validate(operator.as_str())?;
+        ROLE_MEMBERS.save(deps.storage, (Role::Relayer.to_key(), &operator), &true)?;
     }
 
     MARKET_STATS.save(
@@ -55,14 +68,26 @@ pub fn init(
         &MarketStats {
             amount_claimed: Uint128::zero(),
             num_traders: 0,
+            num_buys: 0,
+            num_sells: 0,
+            num_swaps: 0,
+            quote_amount_in: Uint256::zero(),
+            quote_amount_out: Uint256::zero(),
         },
     )?;
 
-    if let Some(addr) = &fees.manager {
-        FEE_MANAGER_ADDR.save(deps.storage, addr)?;
+    let fee_manager = if let Some(addr) = &fees.manager {
+        deps.api.addr_validate(addr.as_str())?
     } else {
-        FEE_MANAGER_ADDR.save(deps.storage, &info.sender)?;
-    }
+        info.sender.clone()
+    };
+
+    FEE_MANAGER_ADDR.save(deps.storage, &fee_manager)?;
+    ROLE_MEMBERS.save(
+        deps.storage,
+        (Role::FeeManager.to_key(), &fee_manager),
+        &true,
+    )?;
 
     for (
         i,
@@ -85,6 +110,7 @@ pub fn init(
                 offset: reserves.quote,
                 supply: reserves.base,
                 k: mul_u256(reserves.base, reserves.quote)?,
+                dust: Uint256::zero(),
             },
         )?;
 
@@ -100,6 +126,7 @@ pub fn init(
                 num_traders: 0,
                 num_buys: 0,
                 num_sells: 0,
+                num_swaps: 0,
             },
         )?;
 
@@ -117,3 +144,63 @@ pub fn init(
 
     Ok(Response::new().add_attribute("action", "instantiate"))
 }
+
+/// Rebuild the secondary indexes of pool accounts and trader infos by saving
+/// each entry again, for state written before the indexes existed.
+pub fn reindex(store: &mut dyn Storage) -> Result<(), ContractError> {
+    let accounts = POOL_ACCOUNTS
+        .range(store, None, None, Order::Ascending)
+        .collect::<Result<Vec<_>, _>>()?;
+    for ((owner, pool_id), account) in accounts {
+        POOL_ACCOUNTS.save(store, (&owner, pool_id), &account)?;
+    }
+
+    let infos = TRADER_INFOS
+        .range(store, None, None, Order::Ascending)
+        .collect::<Result<Vec<_>, _>>()?;
+    for (trader, info) in infos {
+        TRADER_INFOS.save(store, &trader, &info)?;
+    }
+
+    Ok(())
+}
+
+/// Seed roles for state written before role-based access control: add admin,
+/// if given, to the admin role, move the legacy operator into the relayer role
+/// and add the fee manager to the fee manager role. Errors if the contract
+/// would be left without an admin.
+pub fn migrate_roles(
+    deps: DepsMut,
+    admin: Option<Addr>,
+) -> Result<(), ContractError> {
+    if let Some(admin) = admin {
+        let admin = deps.api.addr_validate(admin.as_str())?;
+        ROLE_MEMBERS.save(deps.storage, (Role::Admin.to_key(), &admin), &true)?;
+    }
+
+    if let Some(operator) = LEGACY_OPERATOR_ADDR.may_load(deps.storage)? {
+        ROLE_MEMBERS.save(deps.storage, (Role::Relayer.to_key(), &operator), &true)?;
+        LEGACY_OPERATOR_ADDR.remove(deps.storage);
+    }
+
+    if let Some(fee_manager) = FEE_MANAGER_ADDR.may_load(deps.storage)? {
+        ROLE_MEMBERS.save(
+            deps.storage,
+            (Role::FeeManager.to_key(), &fee_manager),
+            &true,
+        )?;
+    }
+
+    let has_admin = ROLE_MEMBERS
+        .prefix(Role::Admin.to_key())
+        .keys(deps.storage, None, None, Order::Ascending)
+        .next()
+        .is_some();
+    if !has_admin {
+        return Err(ContractError::ValidationError {
+            msg: "an admin is required".to_owned(),
+        });
+    }
+
+    Ok(())
+}
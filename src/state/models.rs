@@ -1,14 +1,64 @@
 use crate::{
     error::ContractError,
-    math::{add_u128, add_u32, div_u256, mul_ratio_u128, sub_u128},
+    math::{
+        add_i256, add_u128, add_u32, div_ceil_u256, mul_ratio_u128, mul_u256, sub_i256, sub_u128,
+        sub_u256,
+    },
+    msg::{PoolAmount, Resolution, TradeAction, TradeKind},
 };
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Addr, Storage, Timestamp, Uint128, Uint256, Uint64};
+use cosmwasm_std::{
+    Addr, Decimal256, Int256, Order, StdError, Storage, Timestamp, Uint128, Uint256, Uint64,
+};
+use cw_storage_plus::Bound;
+
+use super::storage::{
+    PoolId, AGAINST_POSITIONS, CONFIG, MARKET_STATS, POOLS, POOL_ACCOUNTS, POOL_CANDLES,
+    POOL_OHLC_BARS, POOL_STATS, POSITION_COSTS, PROBABILITY_SNAPSHOTS, QUOTE_ACCOUNTS,
+    QUOTE_BALANCES_TOTAL, RECENT_TRADES, TRADER_GRANTS, TRADER_INFOS, TRADE_HISTORY,
+    TRADE_HISTORY_LENGTHS, TRADE_SEQ,
+};
+use super::utils::{allocate_by_price, calc_probabilities};
 
-use super::storage::{PoolId, POOLS, POOL_ACCOUNTS, POOL_OHLC_BARS};
+/// Number of trades kept in each trader's history unless configured otherwise.
+pub const DEFAULT_TRADE_HISTORY_LIMIT: u32 = 100;
 
+/// Contract-wide trading parameters, updated by admins.
 #[cw_serde]
-pub struct Config {}
+#[derive(Default)]
+pub struct Config {
+    /// Minimum quote amount, including fees, per buy from a pool.
+    #[serde(default)]
+    pub min_buy_amount: Uint128,
+    /// Minimum base amount per sell into a pool.
+    #[serde(default)]
+    pub min_sell_amount: Uint128,
+    /// Minimum base amount per swap out of a pool.
+    #[serde(default)]
+    pub min_swap_amount: Uint128,
+    /// Maximum number of trades kept in each trader's history, with older
+    /// trades pruned first. Defaults to DEFAULT_TRADE_HISTORY_LIMIT.
+    #[serde(default)]
+    pub trade_history_limit: Option<u32>,
+}
+
+impl Config {
+    pub fn min_amount(
+        &self,
+        action: TradeKind,
+    ) -> Uint128 {
+        match action {
+            TradeKind::Buy => self.min_buy_amount,
+            TradeKind::Sell => self.min_sell_amount,
+            TradeKind::Swap => self.min_swap_amount,
+        }
+    }
+
+    pub fn trade_history_limit(&self) -> u32 {
+        self.trade_history_limit
+            .unwrap_or(DEFAULT_TRADE_HISTORY_LIMIT)
+    }
+}
 
 #[cw_serde]
 pub struct PoolReserves {
@@ -22,6 +72,10 @@ pub struct Pool {
     pub offset: Uint128,
     pub supply: Uint128,
     pub k: Uint256,
+    /// Surplus of the reserves over the initial constant product, i.e.
+    /// base * quote - k, accumulated from trades rounding in the pool's favor.
+    #[serde(default)]
+    pub dust: Uint256,
 }
 
 impl Pool {
@@ -32,6 +86,44 @@ impl Pool {
         Ok(POOLS.load(store, id)?)
     }
 
+    /// Persist the pool after ensuring its invariants hold.
+    pub fn save(
+        &self,
+        store: &mut dyn Storage,
+        id: PoolId,
+    ) -> Result<(), ContractError> {
+        self.invariant_check()?;
+        Ok(POOLS.save(store, id, self)?)
+    }
+
+    /// Ensure that the reserves have not been drawn below the constant product
+    /// or the virtual quote offset, and that the base reserve is backed by
+    /// supply. Since every swap rounds in the pool's favor, a violation means
+    /// value was extracted from it, e.g. by selling minted complete sets.
+    pub fn invariant_check(&self) -> Result<(), ContractError> {
+        if self.reserves.base.is_zero() || self.reserves.quote.is_zero() {
+            return Err(ContractError::InternalError {
+                msg: "pool reserves depleted".to_owned(),
+            });
+        }
+        if mul_u256(self.reserves.base, self.reserves.quote)? < self.k {
+            return Err(ContractError::InternalError {
+                msg: "pool reserves fall below constant product".to_owned(),
+            });
+        }
+        if self.reserves.quote < self.offset {
+            return Err(ContractError::InsufficientBalance {
+                msg: "pool quote reserve falls below offset".to_owned(),
+            });
+        }
+        if self.reserves.base > self.supply {
+            return Err(ContractError::InternalError {
+                msg: "pool base reserve exceeds supply".to_owned(),
+            });
+        }
+        Ok(())
+    }
+
     /// Buy trades quote tokens for buy-in to the pool.
     pub fn buy(
         &mut self,
@@ -50,27 +142,130 @@ impl Pool {
 
     /// Swap is for reapportioning buy-in between pools, distinct from "buy" and
     /// "sell", which deal with swapping quote tokens in/out of the contract.
+    /// Trades are priced from the reserves' current product rather than k, and
+    /// the reserve paid out of is rounded up, so that any rounding error
+    /// reduces out_amount and stays in the pool for good.
     pub fn swap(
         &mut self,
         in_amount: Uint128,
         is_buy: bool,
     ) -> Result<Uint128, ContractError> {
+        let product = self.product()?;
         let (new_quote_reserve, new_base_reserve, out_amount) = if is_buy {
             let new_quote_reserve = add_u128(self.reserves.quote, in_amount)?;
-            let new_base_reserve = div_u256(self.k, new_quote_reserve)?.try_into().unwrap();
+            let new_base_reserve: Uint128 = div_ceil_u256(product, new_quote_reserve)?
+                .try_into()
+                .map_err(StdError::from)?;
             let out_amount = sub_u128(self.reserves.base, new_base_reserve)?;
             (new_quote_reserve, new_base_reserve, out_amount)
         } else {
             let new_base_reserve = add_u128(self.reserves.base, in_amount)?;
-            let new_quote_reserve = div_u256(self.k, new_base_reserve)?.try_into().unwrap();
+            let new_quote_reserve: Uint128 = div_ceil_u256(product, new_base_reserve)?
+                .try_into()
+                .map_err(StdError::from)?;
             let out_amount = sub_u128(self.reserves.quote, new_quote_reserve)?;
             (new_quote_reserve, new_base_reserve, out_amount)
         };
+        if out_amount.is_zero() {
+            return Err(ContractError::ValidationError {
+                msg: "trade amount too small to yield any output".to_owned(),
+            });
+        }
         self.reserves.base = new_base_reserve;
         self.reserves.quote = new_quote_reserve;
+        self.update_dust()?;
         Ok(out_amount)
     }
 
+    /// Product of the reserves, which never decreases as trades round in the
+    /// pool's favor.
+    fn product(&self) -> Result<Uint256, ContractError> {
+        mul_u256(self.reserves.base, self.reserves.quote)
+    }
+
+    /// Set dust to the reserves' current surplus over the constant product.
+    fn update_dust(&mut self) -> Result<(), ContractError> {
+        self.dust = sub_u256(self.product()?, self.k)?;
+        Ok(())
+    }
+
+    /// Inverse of buy: remove exactly base_out from the base reserve and return
+    /// the quote amount that must be swapped in, rounded up in the pool's favor.
+    pub fn buy_exact_out(
+        &mut self,
+        base_out: Uint128,
+    ) -> Result<Uint128, ContractError> {
+        if base_out >= self.reserves.base {
+            return Err(ContractError::InsufficientBalance {
+                msg: "insufficient base reserve".to_owned(),
+            });
+        }
+        let product = self.product()?;
+        let new_base_reserve = sub_u128(self.reserves.base, base_out)?;
+        let new_quote_reserve: Uint128 = div_ceil_u256(product, new_base_reserve)?
+            .try_into()
+            .map_err(StdError::from)?;
+        let in_amount = sub_u128(new_quote_reserve, self.reserves.quote)?;
+        self.reserves.base = new_base_reserve;
+        self.reserves.quote = new_quote_reserve;
+        self.update_dust()?;
+        Ok(in_amount)
+    }
+
+    /// Inverse of sell: remove exactly quote_out from the quote reserve and
+    /// return the base amount that must be swapped in, rounded up in the pool's
+    /// favor.
+    pub fn sell_exact_out(
+        &mut self,
+        quote_out: Uint128,
+    ) -> Result<Uint128, ContractError> {
+        if quote_out >= self.reserves.quote {
+            return Err(ContractError::InsufficientBalance {
+                msg: "insufficient quote reserve".to_owned(),
+            });
+        }
+        let product = self.product()?;
+        let new_quote_reserve = sub_u128(self.reserves.quote, quote_out)?;
+        let new_base_reserve: Uint128 = div_ceil_u256(product, new_quote_reserve)?
+            .try_into()
+            .map_err(StdError::from)?;
+        let in_amount = sub_u128(new_base_reserve, self.reserves.base)?;
+        self.reserves.base = new_base_reserve;
+        self.reserves.quote = new_quote_reserve;
+        self.update_dust()?;
+        Ok(in_amount)
+    }
+
+    /// Amount of base held by traders rather than the pool's reserve.
+    pub fn circulating(&self) -> Result<Uint128, ContractError> {
+        sub_u128(self.supply, self.reserves.base)
+    }
+
+    /// Issue amount units of position outside of the curve as part of minting
+    /// complete sets. Reserves, and therefore price, are unaffected.
+    pub fn mint_set(
+        &mut self,
+        amount: Uint128,
+    ) -> Result<(), ContractError> {
+        self.supply = add_u128(self.supply, amount)?;
+        Ok(())
+    }
+
+    /// Retire amount units of circulating position as part of redeeming
+    /// complete sets.
+    pub fn redeem_set(
+        &mut self,
+        amount: Uint128,
+    ) -> Result<(), ContractError> {
+        if amount > self.circulating()? {
+            return Err(ContractError::InsufficientBalance {
+                msg: "insufficient circulating supply".to_owned(),
+            });
+        }
+        self.supply = sub_u128(self.supply, amount)?;
+        Ok(())
+    }
+
     pub fn calc_quote_price(
         &self,
         quote_decimals: u8,
@@ -95,15 +290,30 @@ pub struct PoolInfo {
 pub struct MarketStats {
     pub amount_claimed: Uint128,
     pub num_traders: u32,
+    #[serde(default)]
+    pub num_buys: u32,
+    #[serde(default)]
+    pub num_sells: u32,
+    #[serde(default)]
+    pub num_swaps: u32,
+    /// Quote spent on buys, net of fees.
+    #[serde(default)]
+    pub quote_amount_in: Uint256,
+    /// Quote received from sells, net of fees.
+    #[serde(default)]
+    pub quote_amount_out: Uint256,
 }
 
 #[cw_serde]
+#[derive(Default)]
 pub struct TraderStats {
     pub amount_claimed: Uint128,
     pub quote_amount_in: Uint128,
     pub quote_amount_out: Uint128,
     pub num_buys: u32,
     pub num_sells: u32,
+    #[serde(default)]
+    pub num_swaps: u32,
 }
 
 #[cw_serde]
@@ -111,6 +321,88 @@ pub struct TraderInfo {
     pub stats: TraderStats,
 }
 
+impl TraderInfo {
+    /// Apply update to the trader's stats, creating the trader if they have
+    /// none yet. New traders are added to the market's trader count.
+    pub fn upsert(
+        store: &mut dyn Storage,
+        trader: &Addr,
+        update: impl FnOnce(&mut TraderStats) -> Result<(), ContractError>,
+    ) -> Result<Self, ContractError> {
+        let maybe_info = TRADER_INFOS.may_load(store, trader)?;
+        let is_new = maybe_info.is_none();
+        let mut info = maybe_info.unwrap_or_else(|| Self {
+            stats: TraderStats::default(),
+        });
+
+        update(&mut info.stats)?;
+        TRADER_INFOS.save(store, trader, &info)?;
+
+        if is_new {
+            MARKET_STATS.update(store, |mut stats| -> Result<_, ContractError> {
+                stats.num_traders = add_u32(stats.num_traders, 1)?;
+                Ok(stats)
+            })?;
+        }
+
+        Ok(info)
+    }
+}
+
+/// Authority delegated by a granter to a grantee to trade on their behalf.
+#[cw_serde]
+pub struct TraderGrant {
+    pub expires: Option<Timestamp>,
+    pub max_quote_spend: Option<Uint128>,
+    pub quote_spent: Uint128,
+    pub allowed_actions: Vec<TradeKind>,
+}
+
+impl TraderGrant {
+    /// Ensure the grantee may perform the given action for the granter and
+    /// count any quote spent against the grant's spending cap.
+    pub fn consume(
+        store: &mut dyn Storage,
+        granter: &Addr,
+        grantee: &Addr,
+        time: Timestamp,
+        action: TradeKind,
+        quote_spend: Uint128,
+    ) -> Result<Self, ContractError> {
+        let mut grant = TRADER_GRANTS
+            .may_load(store, (granter, grantee))?
+            .ok_or_else(|| ContractError::NotAuthorized {
+                msg: format!("{} has no grant from {}", grantee, granter),
+            })?;
+
+        if grant.expires.map(|t| time >= t).unwrap_or(false) {
+            return Err(ContractError::NotAuthorized {
+                msg: "grant expired".to_owned(),
+            });
+        }
+
+        if !grant.allowed_actions.contains(&action) {
+            return Err(ContractError::NotAuthorized {
+                msg: format!("grant does not allow {:?}", action),
+            });
+        }
+
+        if !quote_spend.is_zero() {
+            grant.quote_spent = add_u128(grant.quote_spent, quote_spend)?;
+            if let Some(max_quote_spend) = grant.max_quote_spend {
+                if grant.quote_spent > max_quote_spend {
+                    return Err(ContractError::NotAuthorized {
+                        msg: "grant spending cap exceeded".to_owned(),
+                    });
+                }
+            }
+            TRADER_GRANTS.save(store, (granter, grantee), &grant)?;
+        }
+
+        Ok(grant)
+    }
+}
+
 #[cw_serde]
 pub struct PoolAccount {
     pub balance: Uint128,
@@ -125,6 +417,7 @@ impl PoolAccount {
         is_positive_delta: bool,
     ) -> Result<Self, ContractError> {
         // Create or insert PoolAccount
+        let mut is_new = false;
         let account = POOL_ACCOUNTS.update(
             store,
             (owner, pool_id),
@@ -136,18 +429,217 @@ impl PoolAccount {
                         sub_u128(account.balance, balance_delta)
                     })?;
                     Ok(account)
-                } else {
+                } else if is_positive_delta {
+                    is_new = true;
                     Ok(Self {
                         balance: balance_delta,
                     })
+                } else {
+                    Err(ContractError::InsufficientBalance {
+                        msg: format!("account not found for pool {}", pool_id),
+                    })
                 }
             },
         )?;
 
+        if !is_positive_delta {
+            AgainstPosition::clamp_legs(store, owner, pool_id, account.balance)?;
+        }
+
+        // Count each account that enters the pool once, however often it
+        // exits and re-enters.
+        if is_new {
+            POOL_STATS.update(store, pool_id, |maybe_stats| -> Result<_, ContractError> {
+                let mut stats = maybe_stats.ok_or_else(|| ContractError::NotAuthorized {
+                    msg: format!("could not load stats for pool {}", pool_id),
+                })?;
+                stats.num_traders = add_u32(stats.num_traders, 1)?;
+                Ok(stats)
+            })?;
+        }
+
+        Ok(account)
+    }
+}
+
+/// Cost basis of a trader's position in a pool and the PnL realized by
+/// reducing it. Cost is added at the quote price paid, including fees, and
+/// removed pro rata as the position is sold.
+#[cw_serde]
+#[derive(Default)]
+pub struct PositionCost {
+    pub cost_basis: Uint128,
+    pub realized_pnl: Int256,
+}
+
+impl PositionCost {
+    /// Add cost to the trader's position in a pool after buying into it.
+    pub fn record_buy(
+        store: &mut dyn Storage,
+        trader: &Addr,
+        pool_id: PoolId,
+        cost: Uint128,
+    ) -> Result<Self, ContractError> {
+        POSITION_COSTS.update(
+            store,
+            (trader, pool_id),
+            |maybe_cost| -> Result<_, ContractError> {
+                let mut position_cost = maybe_cost.unwrap_or_default();
+                position_cost.cost_basis = add_u128(position_cost.cost_basis, cost)?;
+                Ok(position_cost)
+            },
+        )
+    }
+
+    /// Remove the cost of base_amount sold for proceeds from the trader's
+    /// position in a pool, realizing the difference. Must be called after the
+    /// trader's pool account has been debited.
+    pub fn record_sell(
+        store: &mut dyn Storage,
+        trader: &Addr,
+        pool_id: PoolId,
+        base_amount: Uint128,
+        proceeds: Uint128,
+    ) -> Result<Self, ContractError> {
+        let balance = POOL_ACCOUNTS
+            .may_load(store, (trader, pool_id))?
+            .map(|account| account.balance)
+            .unwrap_or_default();
+        let balance_before = add_u128(balance, base_amount)?;
+
+        POSITION_COSTS.update(
+            store,
+            (trader, pool_id),
+            |maybe_cost| -> Result<_, ContractError> {
+                let mut position_cost = maybe_cost.unwrap_or_default();
+                let cost = if balance.is_zero() {
+                    position_cost.cost_basis
+                } else {
+                    mul_ratio_u128(position_cost.cost_basis, base_amount, balance_before)?
+                };
+                position_cost.cost_basis = sub_u128(position_cost.cost_basis, cost)?;
+                position_cost.realized_pnl =
+                    add_i256(position_cost.realized_pnl, sub_i256(proceeds, cost)?)?;
+                Ok(position_cost)
+            },
+        )
+    }
+}
+
+/// Quote held by the contract on behalf of a trader, deposited or credited by
+/// trades, which trades may draw from instead of attached funds.
+#[cw_serde]
+pub struct QuoteAccount {
+    pub balance: Uint128,
+}
+
+impl QuoteAccount {
+    pub fn upsert(
+        store: &mut dyn Storage,
+        owner: &Addr,
+        balance_delta: Uint128,
+        is_positive_delta: bool,
+    ) -> Result<Self, ContractError> {
+        let account =
+            QUOTE_ACCOUNTS.update(store, owner, |maybe_account| -> Result<_, ContractError> {
+                let mut account = maybe_account.unwrap_or(Self {
+                    balance: Uint128::zero(),
+                });
+                if is_positive_delta {
+                    account.balance = add_u128(account.balance, balance_delta)?;
+                } else if account.balance < balance_delta {
+                    return Err(ContractError::InsufficientBalance {
+                        msg: "insufficient internal quote balance".to_owned(),
+                    });
+                } else {
+                    account.balance = sub_u128(account.balance, balance_delta)?;
+                }
+                Ok(account)
+            })?;
+
+        // Keep a running total of what the contract owes across all traders
+        QUOTE_BALANCES_TOTAL.update(store, |total| -> Result<_, ContractError> {
+            if is_positive_delta {
+                add_u128(total, balance_delta)
+            } else {
+                sub_u128(total, balance_delta)
+            }
+        })?;
+
         Ok(account)
     }
 }
 
+/// Synthetic position against a pool, held as base in every other pool.
+#[cw_serde]
+pub struct AgainstPosition {
+    pub shares: Uint128,
+    pub legs: Vec<PoolAmount>,
+}
+
+impl AgainstPosition {
+    /// Split a quote amount across every pool other than pool_id in proportion
+    /// to their current prices. Any remainder from rounding goes to the last
+    /// pool.
+    pub fn allocate(
+        store: &dyn Storage,
+        pool_id: PoolId,
+        amount: Uint128,
+    ) -> Result<Vec<PoolAmount>, ContractError> {
+        if !POOLS.has(store, pool_id) {
+            return Err(ContractError::ValidationError {
+                msg: format!("pool {} does not exist", pool_id),
+            });
+        }
+
+        allocate_by_price(store, amount, Some(pool_id))
+    }
+
+    /// Shrink the owner's legs in pool_id, across all of their positions, so
+    /// that together they do not exceed the owner's balance in the pool.
+    /// Called whenever that balance falls, since the legs are held in it.
+    /// Positions left without legs are removed.
+    pub fn clamp_legs(
+        store: &mut dyn Storage,
+        owner: &Addr,
+        pool_id: PoolId,
+        balance: Uint128,
+    ) -> Result<(), ContractError> {
+        let positions = AGAINST_POSITIONS
+            .prefix(owner)
+            .range(store, None, None, Order::Ascending)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut remaining = balance;
+
+        for (against_pool_id, mut position) in positions {
+            let mut is_changed = false;
+            for leg in position
+                .legs
+                .iter_mut()
+                .filter(|leg| leg.pool_id == pool_id)
+            {
+                if leg.amount > remaining {
+                    leg.amount = remaining;
+                    is_changed = true;
+                }
+                remaining = sub_u128(remaining, leg.amount)?;
+            }
+            if !is_changed {
+                continue;
+            }
+            position.legs.retain(|leg| !leg.amount.is_zero());
+            if position.legs.is_empty() {
+                AGAINST_POSITIONS.remove(store, (owner, against_pool_id));
+            } else {
+                AGAINST_POSITIONS.save(store, (owner, against_pool_id), &position)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
 #[cw_serde]
 pub struct OhlcBar {
     pub o: Uint128,
@@ -172,37 +664,316 @@ impl OhlcBar {
         }
     }
 
+    /// Record a trade at the given price in the pool's bars at every
+    /// resolution. Volume is the quote amount exchanged with the pool's curve.
     pub fn upsert(
         store: &mut dyn Storage,
         pool_id: PoolId,
         time: Timestamp,
         price: Uint128,
-        amount: Uint128,
-    ) -> Result<OhlcBar, ContractError> {
+        quote_amount: Uint128,
+    ) -> Result<(), ContractError> {
         let seconds = time.seconds();
-        let t = seconds - (seconds % 60);
-        POOL_OHLC_BARS.update(
+        for resolution in Resolution::ALL {
+            let span = resolution.seconds() as u64;
+            let t = seconds - (seconds % span);
+            let update = |maybe_bar: Option<OhlcBar>| -> Result<_, ContractError> {
+                maybe_bar
+                    .unwrap_or_else(|| OhlcBar::new(t.into()))
+                    .record(price, quote_amount)
+            };
+            if let Resolution::OneMinute = resolution {
+                POOL_OHLC_BARS.update(store, (pool_id, t), update)?;
+            } else {
+                POOL_CANDLES.update(store, (pool_id, resolution.seconds(), t), update)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn record(
+        mut self,
+        price: Uint128,
+        quote_amount: Uint128,
+    ) -> Result<Self, ContractError> {
+        if self.n > 0 {
+            if price > self.h {
+                self.h = price;
+            }
+            if price < self.l {
+                self.l = price;
+            }
+        } else {
+            self.o = price;
+            self.h = price;
+            self.l = price;
+        }
+        self.c = price;
+        self.v = add_u128(self.v, quote_amount)?;
+        self.n = add_u32(self.n, 1)?;
+        Ok(self)
+    }
+}
+
+/// Implied probability of every pool, indexed by pool ID, as of time t.
+#[cw_serde]
+pub struct ProbabilitySnapshot {
+    pub t: Uint64,
+    pub probabilities: Vec<Decimal256>,
+}
+
+impl ProbabilitySnapshot {
+    /// Record the current implied probabilities at the given time. Later trades
+    /// in the same second replace the snapshot.
+    pub fn record(
+        store: &mut dyn Storage,
+        time: Timestamp,
+    ) -> Result<Self, ContractError> {
+        let snapshot = Self {
+            t: time.seconds().into(),
+            probabilities: calc_probabilities(store)?
+                .into_iter()
+                .map(|(_, probability)| probability)
+                .collect(),
+        };
+        PROBABILITY_SNAPSHOTS.save(store, time.seconds(), &snapshot)?;
+        Ok(snapshot)
+    }
+}
+
+/// A single trade in a trader's history. Amounts are those exchanged with the
+/// pools, so fee_amount is charged on top of a buy's in_amount and deducted
+/// from the quote out of a sell, swap or claim. Complete sets exchange quote for
+/// the same amount of base in every pool, without fees. Prices are the quote
+/// prices of each of the trade's pools, in the same order, after the trade.
+#[cw_serde]
+pub struct TradeRecord {
+    pub action: TradeAction,
+    pub pool_ids: Vec<PoolId>,
+    pub prices: Vec<Uint128>,
+    pub in_amount: Uint128,
+    pub out_amount: Uint128,
+    pub fee_amount: Uint128,
+    pub t: Uint64,
+}
+
+impl TradeRecord {
+    /// Append the trade to the trader's history, pruning the oldest trades
+    /// beyond the configured retention limit, and to the global trade feed,
+    /// returning its sequence number.
+    pub fn append(
+        &self,
+        store: &mut dyn Storage,
+        trader: &Addr,
+    ) -> Result<u64, ContractError> {
+        let index = TRADE_HISTORY_LENGTHS
+            .may_load(store, trader)?
+            .unwrap_or_default();
+        TRADE_HISTORY.save(store, (trader, index), self)?;
+        TRADE_HISTORY_LENGTHS.save(store, trader, &(index + 1))?;
+
+        let limit = CONFIG
+            .may_load(store)?
+            .unwrap_or_default()
+            .trade_history_limit() as u64;
+        let oldest_kept = (index + 1).saturating_sub(limit);
+        let pruned = TRADE_HISTORY
+            .prefix(trader)
+            .keys(
+                store,
+                None,
+                Some(Bound::exclusive(oldest_kept)),
+                Order::Ascending,
+            )
+            .collect::<Result<Vec<_>, _>>()?;
+        for i in pruned {
+            TRADE_HISTORY.remove(store, (trader, i));
+        }
+
+        let seq = TRADE_SEQ.may_load(store)?.unwrap_or_default() + 1;
+        TRADE_SEQ.save(store, &seq)?;
+        RECENT_TRADES.save(
             store,
-            (pool_id, t),
-            |maybe_bar| -> Result<_, ContractError> {
-                let mut bar = maybe_bar.unwrap_or_else(|| OhlcBar::new(t.into()));
-                if bar.n > 0 {
-                    if price > bar.h {
-                        bar.h = price;
-                    }
-                    if price < bar.l {
-                        bar.l = price;
+            seq % RECENT_TRADES_CAPACITY,
+            &SequencedTrade {
+                seq,
+                trader: trader.clone(),
+                trade: self.clone(),
+            },
+        )?;
+
+        Ok(seq)
+    }
+}
+
+/// Number of most recent trades kept in the global trade feed.
+pub const RECENT_TRADES_CAPACITY: u64 = 1000;
+
+/// A trade in the global trade feed, numbered by a gap-free sequence shared by
+/// all traders and starting from 1.
+#[cw_serde]
+pub struct SequencedTrade {
+    pub seq: u64,
+    pub trader: Addr,
+    pub trade: TradeRecord,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Xorshift generator with a fixed seed, so that failing sequences can be
+    /// replayed.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        /// Uniform-ish value in 1..=max, or 0 if max is 0.
+        fn upto(
+            &mut self,
+            max: u128,
+        ) -> u128 {
+            if max == 0 {
+                0
+            } else {
+                1 + (self.next() as u128) % max
+            }
+        }
+    }
+
+    fn new_pool(
+        base: u128,
+        quote: u128,
+    ) -> Pool {
+        Pool {
+            reserves: PoolReserves {
+                base: base.into(),
+                quote: quote.into(),
+            },
+            offset: quote.into(),
+            supply: base.into(),
+            k: mul_u256(base, quote).unwrap(),
+            dust: Uint256::zero(),
+        }
+    }
+
+    /// Apply trade to a copy of the pool, keeping the result only if it
+    /// succeeds, as a failed execute would roll back.
+    fn try_trade<T>(
+        pool: &mut Pool,
+        trade: impl FnOnce(&mut Pool) -> Result<T, ContractError>,
+    ) -> Option<T> {
+        let mut next = pool.clone();
+        let result = trade(&mut next).ok()?;
+        *pool = next;
+        Some(result)
+    }
+
+    #[test]
+    fn random_trades_preserve_invariants_and_extract_no_value() {
+        for seed in 1..=50u64 {
+            let mut rng = Rng(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15));
+            let mut pool = new_pool(
+                rng.upto(1_000_000_000_000) + 1_000,
+                rng.upto(1_000_000_000_000) + 1_000,
+            );
+
+            // Base held by the trader and quote paid in and out of the pool
+            let mut held = 0u128;
+            let mut quote_in = 0u128;
+            let mut quote_out = 0u128;
+
+            for _ in 0..200 {
+                let max_quote = pool.reserves.quote.u128() / 10;
+                let max_base = pool.reserves.base.u128() / 10;
+                match rng.next() % 5 {
+                    0 => {
+                        let amount = rng.upto(max_quote);
+                        if let Some(out) = try_trade(&mut pool, |p| p.buy(amount.into())) {
+                            quote_in += amount;
+                            held += out.u128();
+                        }
+                    },
+                    1 => {
+                        let amount = rng.upto(held);
+                        if let Some(out) = try_trade(&mut pool, |p| p.sell(amount.into())) {
+                            quote_out += out.u128();
+                            held -= amount;
+                        }
+                    },
+                    2 => {
+                        if rng.next() & 1 == 0 {
+                            let amount = rng.upto(max_quote);
+                            if let Some(out) = try_trade(&mut pool, |p| p.swap(amount.into(), true))
+                            {
+                                quote_in += amount;
+                                held += out.u128();
+                            }
+                        } else {
+                            let amount = rng.upto(held);
+                            if let Some(out) =
+                                try_trade(&mut pool, |p| p.swap(amount.into(), false))
+                            {
+                                quote_out += out.u128();
+                                held -= amount;
+                            }
+                        }
+                    },
+                    3 => {
+                        let amount = rng.upto(max_base);
+                        if let Some(cost) = try_trade(&mut pool, |p| p.buy_exact_out(amount.into()))
+                        {
+                            quote_in += cost.u128();
+                            held += amount;
+                        }
+                    },
+                    _ => {
+                        let amount = rng.upto(max_quote);
+                        let mut next = pool.clone();
+                        if let Ok(base_in) = next.sell_exact_out(amount.into()) {
+                            if base_in.u128() <= held {
+                                pool = next;
+                                quote_out += amount;
+                                held -= base_in.u128();
+                            }
+                        }
+                    },
+                }
+
+                pool.invariant_check().unwrap();
+                assert_eq!(
+                    pool.dust,
+                    mul_u256(pool.reserves.base, pool.reserves.quote).unwrap() - pool.k
+                );
+
+                // Buying and immediately selling back never returns more quote
+                let amount = rng.upto(max_quote);
+                let mut round_trip = pool.clone();
+                if let Ok(out) = round_trip.buy(amount.into()) {
+                    if let Ok(back) = round_trip.sell(out) {
+                        assert!(back.u128() <= amount, "seed {}: round trip gained", seed);
                     }
-                } else {
-                    bar.o = price;
-                    bar.h = price;
-                    bar.l = price;
                 }
-                bar.c = price;
-                bar.v = add_u128(bar.v, amount)?;
-                bar.n = add_u32(bar.n, 1)?;
-                Ok(bar)
-            },
-        )
+            }
+
+            // Selling everything still held never returns more than was paid in
+            if held > 0 {
+                quote_out += pool.sell(held.into()).map(|x| x.u128()).unwrap_or(0);
+                pool.invariant_check().unwrap();
+            }
+            assert!(
+                quote_out <= quote_in,
+                "seed {}: {} out > {} in",
+                seed,
+                quote_out,
+                quote_in
+            );
+        }
     }
 }
@@ -1,15 +1,21 @@
 use crate::{msg::SwapStats, token::Token};
-use cosmwasm_std::{Addr, Timestamp, Uint128};
-use cw_storage_plus::{Item, Map};
+use cosmwasm_std::{Addr, Binary, Timestamp, Uint128};
+use cw_storage_plus::{Index, IndexList, IndexedMap, Item, Map, MultiIndex};
 
 use crate::msg::PoolStats;
 
-use super::models::{Config, MarketStats, OhlcBar, Pool, PoolAccount, PoolInfo, TraderInfo};
+use super::models::{
+    AgainstPosition, Config, MarketStats, OhlcBar, Pool, PoolAccount, PoolInfo, PositionCost,
+    ProbabilitySnapshot, QuoteAccount, SequencedTrade, TradeRecord, TraderGrant, TraderInfo,
+};
 
 pub type PoolId = u8;
 
 pub const CONFIG: Item<Config> = Item::new("config");
-pub const OPERATOR_ADDR: Item<Addr> = Item::new("operator_addr");
+pub const ROLE_MEMBERS: Map<(&str, &Addr), bool> = Map::new("role_members");
+/// Operator address from before role-based access control, moved into the
+/// relayer role on migration.
+pub const LEGACY_OPERATOR_ADDR: Item<Addr> = Item::new("operator_addr");
 pub const QUOTE_TOKEN: Item<Token> = Item::new("quote_token");
 pub const QUOTE_DECIMALS: Item<u8> = Item::new("quote_decimals");
 pub const QUOTE_SYMBOL: Item<String> = Item::new("quote_symbol");
@@ -17,15 +23,108 @@ pub const MARKET_STATS: Item<MarketStats> = Item::new("stats");
 pub const BUY_FEE_PCT: Item<Uint128> = Item::new("buy_fee_pct");
 pub const SELL_FEE_PCT: Item<Uint128> = Item::new("sell_fee_pct");
 pub const SWAP_FEE_PCT: Item<Uint128> = Item::new("swap_fee_pct");
+pub const CLAIM_FEE_PCT: Item<Uint128> = Item::new("claim_fee_pct");
 pub const FEE_MANAGER_ADDR: Item<Addr> = Item::new("fee_manager_addr");
 pub const START_TIME: Item<Timestamp> = Item::new("start_time");
 pub const STOP_TIME: Item<Timestamp> = Item::new("stop_time");
+pub const WINNING_POOL_ID: Item<PoolId> = Item::new("winning_pool_id");
+pub const MARKET_PAUSED: Item<bool> = Item::new("market_paused");
+pub const CLAIMS_PAUSED: Item<bool> = Item::new("claims_paused");
+pub const PAUSED_POOLS: Map<PoolId, bool> = Map::new("paused_pools");
 pub const HAS_CLAIMED: Map<&Addr, bool> = Map::new("has_claimed");
 pub const AMOUNT_CLAIMED: Item<Uint128> = Item::new("amount_claimed");
+pub const SET_COLLATERAL: Item<Uint128> = Item::new("set_collateral");
+pub const QUOTE_BALANCES_TOTAL: Item<Uint128> = Item::new("quote_balances_total");
+pub const QUOTE_ACCOUNTS: Map<&Addr, QuoteAccount> = Map::new("quote_accounts");
 pub const POOLS: Map<PoolId, Pool> = Map::new("pools");
 pub const POOL_INFOS: Map<PoolId, PoolInfo> = Map::new("pool_infos");
 pub const POOL_STATS: Map<PoolId, PoolStats> = Map::new("pool_stats");
 pub const SWAP_STATS: Map<(PoolId, PoolId), SwapStats> = Map::new("swap_stats");
-pub const TRADER_INFOS: Map<&Addr, TraderInfo> = Map::new("trader_infos");
-pub const POOL_ACCOUNTS: Map<(&Addr, PoolId), PoolAccount> = Map::new("pool_accounts");
+pub const TRADER_INFOS: IndexedMap<&Addr, TraderInfo, TraderInfoIndexes> = IndexedMap::new(
+    "trader_infos",
+    TraderInfoIndexes {
+        volume: MultiIndex::new(trader_volume, "trader_infos", "trader_infos__volume"),
+        claimed: MultiIndex::new(trader_claimed, "trader_infos", "trader_infos__claimed"),
+    },
+);
+pub const POOL_ACCOUNTS: IndexedMap<(&Addr, PoolId), PoolAccount, PoolAccountIndexes> =
+    IndexedMap::new(
+        "pool_accounts",
+        PoolAccountIndexes {
+            pool_balance: MultiIndex::new(
+                pool_account_pool_balance,
+                "pool_accounts",
+                "pool_accounts__pool_balance",
+            ),
+        },
+    );
+pub const POSITION_COSTS: Map<(&Addr, PoolId), PositionCost> = Map::new("position_costs");
 pub const POOL_OHLC_BARS: Map<(PoolId, u64), OhlcBar> = Map::new("pool_ohlc_bars");
+/// Trades of each trader by index, with the oldest pruned beyond the
+/// configured retention limit.
+pub const TRADE_HISTORY: Map<(&Addr, u64), TradeRecord> = Map::new("trade_history");
+/// Number of trades ever appended to each trader's history.
+pub const TRADE_HISTORY_LENGTHS: Map<&Addr, u64> = Map::new("trade_history_lengths");
+/// Sequence number of the most recent trade in the global trade feed.
+pub const TRADE_SEQ: Item<u64> = Item::new("trade_seq");
+/// Ring buffer of the most recent trades, keyed by sequence number modulo
+/// RECENT_TRADES_CAPACITY.
+pub const RECENT_TRADES: Map<u64, SequencedTrade> = Map::new("recent_trades");
+pub const PROBABILITY_SNAPSHOTS: Map<u64, ProbabilitySnapshot> = Map::new("probability_snapshots");
+/// Bars at resolutions coarser than one minute, keyed by resolution in seconds.
+pub const POOL_CANDLES: Map<(PoolId, u32, u64), OhlcBar> = Map::new("pool_candles");
+pub const TRADER_PUBKEYS: Map<&Addr, Binary> = Map::new("trader_pubkeys");
+pub const INTENT_NONCES: Map<&Addr, u64> = Map::new("intent_nonces");
+pub const TRADER_GRANTS: Map<(&Addr, &Addr), TraderGrant> = Map::new("trader_grants");
+pub const AGAINST_POSITIONS: Map<(&Addr, PoolId), AgainstPosition> = Map::new("against_positions");
+
+pub struct TraderInfoIndexes<'a> {
+    /// Total quote traded, in and out.
+    pub volume: MultiIndex<'a, u128, TraderInfo, Addr>,
+    /// Total quote claimed, net of claim fees.
+    pub claimed: MultiIndex<'a, u128, TraderInfo, Addr>,
+}
+
+impl<'a> IndexList<TraderInfo> for TraderInfoIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<TraderInfo>> + '_> {
+        let v: Vec<&dyn Index<TraderInfo>> = vec![&self.volume, &self.claimed];
+        Box::new(v.into_iter())
+    }
+}
+
+fn trader_volume(
+    _pk: &[u8],
+    info: &TraderInfo,
+) -> u128 {
+    info.stats
+        .quote_amount_in
+        .u128()
+        .saturating_add(info.stats.quote_amount_out.u128())
+}
+
+fn trader_claimed(
+    _pk: &[u8],
+    info: &TraderInfo,
+) -> u128 {
+    info.stats.amount_claimed.u128()
+}
+
+pub struct PoolAccountIndexes<'a> {
+    /// Accounts by pool and then balance, for ranking a pool's holders.
+    pub pool_balance: MultiIndex<'a, (PoolId, u128), PoolAccount, (Addr, PoolId)>,
+}
+
+impl<'a> IndexList<PoolAccount> for PoolAccountIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<PoolAccount>> + '_> {
+        let v: Vec<&dyn Index<PoolAccount>> = vec![&self.pool_balance];
+        Box::new(v.into_iter())
+    }
+}
+
+fn pool_account_pool_balance(
+    pk: &[u8],
+    account: &PoolAccount,
+) -> (PoolId, u128) {
+    // The pool ID is the single trailing byte of the (Addr, PoolId) key
+    (pk[pk.len() - 1], account.balance.u128())
+}
@@ -1,27 +1,330 @@
-use cosmwasm_std::{Addr, Api, Storage};
+use cosmwasm_std::{
+    Addr, Api, BlockInfo, Decimal256, Order, QuerierWrapper, StdError, Storage, Timestamp, Uint128,
+};
 
-use crate::error::ContractError;
+use crate::{
+    error::ContractError,
+    math::{add_u128, mul_pct_u128, mul_ratio_u128, sub_u128},
+    msg::{PoolAmount, PoolOdds, Role, TradeKind},
+};
 
-use super::storage::OPERATOR_ADDR;
+use super::{
+    models::{Pool, TraderGrant},
+    storage::{
+        PoolId, AMOUNT_CLAIMED, CLAIMS_PAUSED, CLAIM_FEE_PCT, CONFIG, MARKET_PAUSED, PAUSED_POOLS,
+        POOLS, QUOTE_BALANCES_TOTAL, QUOTE_DECIMALS, QUOTE_TOKEN, ROLE_MEMBERS, SET_COLLATERAL,
+        WINNING_POOL_ID,
+    },
+};
 
-/// Return the tx sender address of the initiator address if exists AND the tx
-/// sender is the registered "operator" address.
-pub fn resolve_initiator(
+/// Return true if the address is a member of the given role.
+pub fn has_role(
     store: &dyn Storage,
+    role: Role,
+    addr: &Addr,
+) -> Result<bool, ContractError> {
+    Ok(ROLE_MEMBERS
+        .may_load(store, (role.to_key(), addr))?
+        .unwrap_or(false))
+}
+
+/// Return a NotAuthorized error unless the address has the given role.
+pub fn ensure_role(
+    store: &dyn Storage,
+    role: Role,
+    addr: &Addr,
+) -> Result<(), ContractError> {
+    if !has_role(store, role, addr)? {
+        return Err(ContractError::NotAuthorized {
+            msg: format!("{} role required", role.to_key()),
+        });
+    }
+    Ok(())
+}
+
+/// Return the tx sender address or the initiator address if exists AND the tx
+/// sender holds a grant from the initiator that permits the given action and
+/// quote spend. Relayers act for users only through signed intents.
+pub fn resolve_initiator(
+    store: &mut dyn Storage,
     api: &dyn Api,
+    time: Timestamp,
     sender: &Addr,
     maybe_initiator: Option<Addr>,
+    action: TradeKind,
+    quote_spend: Uint128,
 ) -> Result<Addr, ContractError> {
     if let Some(candidate_initiator) = maybe_initiator {
-        if let Some(operator_addr) = OPERATOR_ADDR.may_load(store)? {
-            if operator_addr == sender {
-                return Ok(api.addr_validate(candidate_initiator.as_str())?);
-            }
+        let initiator = api.addr_validate(candidate_initiator.as_str())?;
+        if initiator == sender {
+            return Ok(initiator);
         }
-        Err(ContractError::NotAuthorized {
-            msg: "Only contract operator can specify initiator".to_owned(),
-        })
+        TraderGrant::consume(store, &initiator, sender, time, action, quote_spend)?;
+        Ok(initiator)
     } else {
         Ok(sender.clone())
     }
 }
+
+/// Return a Paused error if trading is paused for the whole market or for the
+/// given pool, or a NotAuthorized error if the market has been resolved, since
+/// trading against a known outcome would drain the other pools.
+pub fn ensure_pool_not_paused(
+    store: &dyn Storage,
+    pool_id: PoolId,
+) -> Result<(), ContractError> {
+    if WINNING_POOL_ID.exists(store) {
+        return Err(ContractError::NotAuthorized {
+            msg: "the market has been resolved".to_owned(),
+        });
+    }
+    if MARKET_PAUSED.may_load(store)?.unwrap_or(false) {
+        return Err(ContractError::Paused {
+            msg: "trading is paused".to_owned(),
+        });
+    }
+    if PAUSED_POOLS.has(store, pool_id) {
+        return Err(ContractError::Paused {
+            msg: format!("trading is paused in pool {}", pool_id),
+        });
+    }
+    Ok(())
+}
+
+/// Return a Paused error if claims are paused.
+pub fn ensure_claims_not_paused(store: &dyn Storage) -> Result<(), ContractError> {
+    if CLAIMS_PAUSED.may_load(store)?.unwrap_or(false) {
+        return Err(ContractError::Paused {
+            msg: "claims are paused".to_owned(),
+        });
+    }
+    Ok(())
+}
+
+/// Return a ValidationError if amount is below the configured minimum size for
+/// the given kind of trade.
+pub fn ensure_min_trade_size(
+    store: &dyn Storage,
+    action: TradeKind,
+    amount: Uint128,
+) -> Result<(), ContractError> {
+    let min_amount = CONFIG
+        .may_load(store)?
+        .unwrap_or_default()
+        .min_amount(action);
+    if amount < min_amount {
+        return Err(ContractError::ValidationError {
+            msg: format!(
+                "trade amount {} is below the minimum of {}",
+                amount, min_amount
+            ),
+        });
+    }
+    Ok(())
+}
+
+/// Split a quote amount across every pool, other than excluded_pool_id if
+/// given, in proportion to their current prices. Any remainder from rounding
+/// goes to the last pool.
+pub fn allocate_by_price(
+    store: &dyn Storage,
+    amount: Uint128,
+    excluded_pool_id: Option<PoolId>,
+) -> Result<Vec<PoolAmount>, ContractError> {
+    let quote_decimals = QUOTE_DECIMALS.load(store)?;
+    let mut prices: Vec<(PoolId, Uint128)> = Vec::with_capacity(4);
+    let mut total_price = Uint128::zero();
+
+    for result in POOLS.range(store, None, None, Order::Ascending) {
+        let (pool_id, pool) = result?;
+        if Some(pool_id) != excluded_pool_id {
+            let price = pool.calc_quote_price(quote_decimals)?;
+            total_price = add_u128(total_price, price)?;
+            prices.push((pool_id, price));
+        }
+    }
+
+    if prices.is_empty() || total_price.is_zero() {
+        return Err(ContractError::ValidationError {
+            msg: "no pools to allocate to".to_owned(),
+        });
+    }
+
+    let mut unallocated_amount = amount;
+    let mut allocations: Vec<PoolAmount> = Vec::with_capacity(prices.len());
+
+    for (i, (pool_id, price)) in prices.iter().enumerate() {
+        let pool_amount = if i + 1 == prices.len() {
+            unallocated_amount
+        } else {
+            mul_ratio_u128(amount, *price, total_price)?
+        };
+        unallocated_amount = sub_u128(unallocated_amount, pool_amount)?;
+        allocations.push(PoolAmount {
+            pool_id: *pool_id,
+            amount: pool_amount,
+        });
+    }
+
+    Ok(allocations)
+}
+
+/// Return an Expired error if the block time is past the deadline or the block
+/// height is past valid_until_height.
+pub fn ensure_not_expired(
+    block: &BlockInfo,
+    deadline: Option<Timestamp>,
+    valid_until_height: Option<u64>,
+) -> Result<(), ContractError> {
+    if let Some(deadline) = deadline {
+        if block.time > deadline {
+            return Err(ContractError::Expired {
+                msg: format!("deadline {} has passed", deadline.seconds()),
+            });
+        }
+    }
+    if let Some(height) = valid_until_height {
+        if block.height > height {
+            return Err(ContractError::Expired {
+                msg: format!("valid_until_height {} has passed", height),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Total quote available to winners: quote swapped into all pools beyond their
+/// initial offsets, plus collateral backing complete sets. Pools never hold
+/// less than their offset, as Pool::invariant_check rejects such trades.
+pub fn calc_net_winnings(store: &dyn Storage) -> Result<Uint128, ContractError> {
+    let mut total_quote = SET_COLLATERAL.may_load(store)?.unwrap_or_default();
+    let mut total_offset = Uint128::zero();
+    for result in POOLS.range(store, None, None, Order::Ascending) {
+        let (_, pool) = result?;
+        total_quote = add_u128(total_quote, pool.reserves.quote)?;
+        total_offset = add_u128(total_offset, pool.offset)?;
+    }
+    sub_u128(total_quote, total_offset)
+}
+
+/// Quote the contract owes: winnings not yet claimed plus traders' internal
+/// quote balances.
+pub fn calc_liabilities(store: &dyn Storage) -> Result<Uint128, ContractError> {
+    let unclaimed = sub_u128(
+        calc_net_winnings(store)?,
+        AMOUNT_CLAIMED.may_load(store)?.unwrap_or_default(),
+    )?;
+    add_u128(
+        unclaimed,
+        QUOTE_BALANCES_TOTAL.may_load(store)?.unwrap_or_default(),
+    )
+}
+
+/// Quote that balance units of position in pool_id would claim if the pool
+/// won, returned as the claim amount and the portion of it taken as the claim
+/// fee.
+pub fn calc_claim(
+    store: &dyn Storage,
+    pool_id: PoolId,
+    balance: Uint128,
+) -> Result<(Uint128, Uint128), ContractError> {
+    let circulating = Pool::load(store, pool_id)?.circulating()?;
+    if circulating.is_zero() {
+        return Ok((Uint128::zero(), Uint128::zero()));
+    }
+    let claim_amount = mul_ratio_u128(calc_net_winnings(store)?, balance, circulating)?;
+    let fee_pct = CLAIM_FEE_PCT.may_load(store)?.unwrap_or_default();
+    Ok((claim_amount, mul_pct_u128(claim_amount, fee_pct)?))
+}
+
+/// Quote paid per unit of position in pool_id, net of the claim fee, if the
+/// pool won.
+pub fn calc_payout_multiple(
+    store: &dyn Storage,
+    pool_id: PoolId,
+) -> Result<Decimal256, ContractError> {
+    let circulating = Pool::load(store, pool_id)?.circulating()?;
+    if circulating.is_zero() {
+        return Ok(Decimal256::zero());
+    }
+    let (claim_amount, fee_amount) = calc_claim(store, pool_id, circulating)?;
+    Ok(Decimal256::from_ratio(
+        sub_u128(claim_amount, fee_amount)?,
+        circulating,
+    ))
+}
+
+/// Implied probability of every pool: its quote price normalized by the sum of
+/// all pools' prices.
+pub fn calc_probabilities(store: &dyn Storage) -> Result<Vec<(PoolId, Decimal256)>, ContractError> {
+    let prices = POOLS
+        .range(store, None, None, Order::Ascending)
+        .map(|result| {
+            result.map(|(pool_id, pool)| {
+                (
+                    pool_id,
+                    Decimal256::from_ratio(pool.reserves.quote, pool.reserves.base),
+                )
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let total_price = prices
+        .iter()
+        .try_fold(Decimal256::zero(), |total, (_, price)| {
+            total.checked_add(*price)
+        })
+        .map_err(|e| ContractError::Std(StdError::overflow(e)))?;
+
+    Ok(prices
+        .into_iter()
+        .map(|(pool_id, price)| {
+            if total_price.is_zero() {
+                (pool_id, Decimal256::zero())
+            } else {
+                (pool_id, price / total_price)
+            }
+        })
+        .collect())
+}
+
+/// Implied probability, decimal odds and payout multiple of every pool.
+pub fn calc_odds(store: &dyn Storage) -> Result<Vec<PoolOdds>, ContractError> {
+    let mut odds: Vec<PoolOdds> = Vec::with_capacity(2);
+
+    for (pool_id, probability) in calc_probabilities(store)? {
+        let decimal_odds = if probability.is_zero() {
+            Decimal256::zero()
+        } else {
+            Decimal256::one() / probability
+        };
+        odds.push(PoolOdds {
+            pool_id,
+            probability,
+            decimal_odds,
+            payout_multiple: calc_payout_multiple(store, pool_id)?,
+        });
+    }
+
+    Ok(odds)
+}
+
+/// Return an error unless the contract's quote balance, less an amount about
+/// to be transferred out, covers its liabilities.
+pub fn ensure_solvent(
+    store: &dyn Storage,
+    querier: QuerierWrapper,
+    contract_addr: &Addr,
+    pending_out_amount: Uint128,
+) -> Result<(), ContractError> {
+    let balance = QUOTE_TOKEN
+        .load(store)?
+        .query_balance(querier, contract_addr)?;
+    let liabilities = calc_liabilities(store)?;
+    if balance < add_u128(liabilities, pending_out_amount)? {
+        return Err(ContractError::InsufficientFunds {
+            msg: "contract quote balance does not cover its liabilities".to_owned(),
+        });
+    }
+    Ok(())
+}
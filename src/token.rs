@@ -94,19 +94,32 @@ impl Token {
         })
     }
 
+    /// Total amount of this token contained in the given funds.
+    pub fn amount_in_funds(
+        &self,
+        funds: &[Coin],
+    ) -> Uint128 {
+        if let Self::Denom(denom) = self {
+            funds
+                .iter()
+                .filter(|c| c.denom == *denom)
+                .map(|c| c.amount)
+                .sum()
+        } else {
+            Uint128::zero()
+        }
+    }
+
     pub fn has_in_funds(
         &self,
-        funds_to_search: &Vec<Coin>,
+        funds_to_search: &[Coin],
         exact_amount: Option<Uint128>,
     ) -> bool {
         if let Self::Denom(denom) = self {
             funds_to_search
                 .iter()
                 .find(|c| {
-                    c.denom == *denom
-                        && (exact_amount
-                            .and_then(|n| Some(n == c.amount))
-                            .unwrap_or(true))
+                    c.denom == *denom && (exact_amount.map(|n| n == c.amount).unwrap_or(true))
                 })
                 .is_some()
         } else {